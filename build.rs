@@ -2,7 +2,87 @@
 # `ChannelZ`
 */
 
-/// # Build Settings Flags.
+use std::fmt::Write;
+
+
+
+/// # Supported File Types.
+///
+/// This is the one and only registry of file types ChannelZ encodes by
+/// default. The matcher and help screen are both generated from it.
+///
+/// Optional bits are wrapped in parentheses, so "htm(l)" covers both "htm"
+/// and "html".
+///
+/// Each type is paired with the Brotli mode best suited to its content.
+///
+/// Note: the `Cargo.toml` packaging metadata and `README.md` have to be
+/// updated by hand, but the test suite will complain if they fall out of
+/// sync.
+const FILE_TYPES: [(&str, Mode); 35] = [
+	("appcache", Mode::Generic),
+	("atom", Mode::Text),
+	("bmp", Mode::Generic),
+	("css", Mode::Text),
+	("csv", Mode::Generic),
+	("doc(x)", Mode::Generic),
+	("eot", Mode::Font),
+	("geojson", Mode::Text),
+	("htc", Mode::Generic),
+	("htm(l)", Mode::Text),
+	("ico", Mode::Generic),
+	("ics", Mode::Generic),
+	("js", Mode::Text),
+	("json", Mode::Text),
+	("jsonld", Mode::Text),
+	("manifest", Mode::Generic),
+	("md", Mode::Generic),
+	("mjs", Mode::Text),
+	("otf", Mode::Font),
+	("pdf", Mode::Generic),
+	("rdf", Mode::Text),
+	("rss", Mode::Text),
+	("svg", Mode::Text),
+	("ttf", Mode::Font),
+	("txt", Mode::Generic),
+	("vcard", Mode::Generic),
+	("vcs", Mode::Generic),
+	("vtt", Mode::Generic),
+	("wasm", Mode::Generic),
+	("webmanifest", Mode::Text),
+	("xhtm(l)", Mode::Text),
+	("xls(x)", Mode::Generic),
+	("xml", Mode::Text),
+	("xsl", Mode::Text),
+	("y(a)ml", Mode::Generic),
+];
+
+/// # Max Extension Length.
+///
+/// This matches `dowser::Extension`'s internal buffer size. Anything longer
+/// has to be matched manually.
+const EXT_SIZE: usize = 8;
+
+
+
+#[derive(Debug, Clone, Copy)]
+/// # Brotli Mode.
+///
+/// These correspond to the `BrotliMode` variants in `enc.rs`.
+enum Mode {
+	/// # Generic.
+	Generic,
+
+	/// # Text (UTF-8).
+	Text,
+
+	/// # Font (WOFF 2.0).
+	Font,
+}
+
+
+
+/// # Build Settings Flags, Extensions.
 fn main() {
 	println!("cargo:rerun-if-env-changed=CARGO_PKG_VERSION");
 
@@ -10,11 +90,62 @@ fn main() {
 		.private()
 		.with_flag("Brotli", None)
 		.with_flag("Gzip", None)
-		.with_alias("All", ["Brotli", "Gzip"], Some("# All Encoders."))
-		.with_flag("Clean", Some("# Clean Old Br/Gz First."))
-		.with_complex_flag("CleanOnly", ["Clean"], Some("# Clean Old Br/Gz and Exit."))
+		.with_flag("Zstd", None)
+		.with_alias("All", ["Brotli", "Gzip", "Zstd"], Some("# All Encoders."))
+		.with_flag("Dcb", Some("# Dictionary-Compressed Brotli."))
+		.with_flag("Dcz", Some("# Dictionary-Compressed Zstd."))
+		.with_flag("Clean", Some("# Clean Old Br/Gz/Zst First."))
+		.with_complex_flag("CleanOnly", ["Clean"], Some("# Clean Old Br/Gz/Zst and Exit."))
 		.with_flag("Force", Some("# Crunch All Files.\n\nIgnore the built-in extension times and crunch all the files found."))
 		.save(out_path("flags.rs"));
+
+	build_extensions();
+	build_help();
+}
+
+/// # Build Extension Matcher.
+///
+/// Expand the file types into individual extensions and feed them — along
+/// with their Brotli modes — to the `ext!` macro in `ext.rs`.
+fn build_extensions() {
+	let mut exts: Vec<(String, Mode)> = FILE_TYPES.iter()
+		.flat_map(|(v, mode)| expand(v).into_iter().map(|e| (e, *mode)))
+		.collect();
+	exts.sort_by(|a, b| a.0.len().cmp(&b.0.len()).then_with(|| a.0.cmp(&b.0)));
+	exts.dedup_by(|a, b| a.0 == b.0);
+
+	let mut out = String::from("ext!{\n");
+	for (e, mode) in exts.iter().filter(|(e, _)| e.len() <= EXT_SIZE) {
+		writeln!(&mut out, "\tEXT_{} \"{e}\" {mode:?},", e.to_ascii_uppercase()).unwrap();
+	}
+	out.push_str("\t@long\n");
+	for (e, mode) in exts.iter().filter(|(e, _)| EXT_SIZE < e.len()) {
+		writeln!(&mut out, "\tEXT_{} \"{e}\" {mode:?},", e.to_ascii_uppercase()).unwrap();
+	}
+	out.push_str("}\n");
+
+	std::fs::write(out_path("ext.rs"), out).expect("Unable to save ext.rs.");
+}
+
+/// # Build Help (File Types).
+///
+/// Word-wrap the file types for the help screen.
+fn build_help() {
+	let out = wrap(4, 80).join("\n");
+	std::fs::write(out_path("help-file-types.txt"), out)
+		.expect("Unable to save help-file-types.txt.");
+}
+
+/// # Expand File Type.
+///
+/// Return the extension(s) covered by a given file type, e.g. "doc(x)" to
+/// "doc" and "docx".
+fn expand(src: &str) -> Vec<String> {
+	if let Some((a, rest)) = src.split_once('(') {
+		let (b, c) = rest.split_once(')').expect("Unclosed file type.");
+		vec![format!("{a}{c}"), format!("{a}{b}{c}")]
+	}
+	else { vec![src.to_owned()] }
 }
 
 /// # Output Path.
@@ -25,3 +156,29 @@ fn out_path(stub: &str) -> std::path::PathBuf {
 		.expect("Missing OUT_DIR.")
 		.join(stub)
 }
+
+/// # Word-Wrap File Types.
+///
+/// Return the file types as semicolon-separated lines no wider than `width`,
+/// each prefixed with `indent` spaces.
+fn wrap(indent: usize, width: usize) -> Vec<String> {
+	let indent = " ".repeat(indent);
+	let mut out = Vec::new();
+	let mut line = indent.clone();
+	for (k, (v, _)) in FILE_TYPES.iter().enumerate() {
+		let v =
+			if k + 1 == FILE_TYPES.len() { (*v).to_owned() }
+			else { format!("{v};") };
+
+		if line.len() == indent.len() { line.push_str(&v); }
+		else if line.len() + 1 + v.len() <= width {
+			line.push(' ');
+			line.push_str(&v);
+		}
+		else {
+			out.push(std::mem::replace(&mut line, format!("{indent}{v}")));
+		}
+	}
+	out.push(line);
+	out
+}
@@ -5,7 +5,12 @@ This module helps tidy up the file sizes being passed back and forth all over
 the place.
 */
 
-use crate::Flags;
+use crate::{
+	dict::available_dictionary,
+	Flags,
+	print_msg,
+	report::json_str,
+};
 use dactyl::{
 	NiceU64,
 	NicePercent,
@@ -16,6 +21,7 @@ use fyi_msg::{
 	Msg,
 };
 use std::{
+	fmt::Write,
 	num::NonZeroU64,
 	ops::{
 		Add,
@@ -25,14 +31,19 @@ use std::{
 
 
 
+/// # Encoded Formats.
+const ENCODED: Flags = Flags::All.with(Flags::Dcb).with(Flags::Dcz);
+
+
+
 #[derive(Debug, Clone, Copy)]
 /// # Encoder Totals.
 ///
 /// This struct is used to represent the various file sizes after a single
 /// encoding pass.
 ///
-/// The brotli and gzip totals are only set if smaller than the original; if
-/// `None`, they're treated as equivalent (zero savings).
+/// The encoded totals are only set if smaller than the original; if `None`,
+/// they're treated as equivalent (zero savings).
 pub(super) struct EncoderTotals {
 	/// # Raw Size.
 	raw: NonZeroU64,
@@ -40,8 +51,39 @@ pub(super) struct EncoderTotals {
 	/// # Brotli Size.
 	br: Option<NonZeroU64>,
 
+	/// # Exhaustive Brotli Savings.
+	///
+	/// The number of bytes the extra exhaustive brotli trials saved, if any.
+	br_trials: u64,
+
 	/// # Gzip Size.
 	gz: Option<NonZeroU64>,
+
+	/// # Zstd Size.
+	zst: Option<NonZeroU64>,
+
+	/// # Dictionary-Compressed Brotli Size.
+	dcb: Option<NonZeroU64>,
+
+	/// # Dictionary-Compressed Zstd Size.
+	dcz: Option<NonZeroU64>,
+
+	/// # Dictionary Hash.
+	///
+	/// The SHA-256 of the dictionary used for the `dcb`/`dcz` copies, if any.
+	dict: Option<[u8; 32]>,
+
+	/// # Up-to-Date?
+	///
+	/// This is `true` when the encoded copies were left as-is because they
+	/// were already current.
+	fresh: bool,
+
+	/// # Written Copies.
+	written: Flags,
+
+	/// # Removed Copies.
+	removed: Flags,
 }
 
 impl EncoderTotals {
@@ -49,13 +91,69 @@ impl EncoderTotals {
 	///
 	/// Return a new instance with the raw size thusly set.
 	pub(super) const fn new(raw: NonZeroU64) -> Self {
-		Self { raw, br: None, gz: None }
+		Self {
+			raw,
+			br: None,
+			br_trials: 0,
+			gz: None,
+			zst: None,
+			dcb: None,
+			dcz: None,
+			dict: None,
+			fresh: false,
+			written: Flags::None,
+			removed: Flags::None,
+		}
 	}
 
+	/// # New (Up-to-Date).
+	///
+	/// Same as `EncoderTotals::new`, but for sources whose encoded copies
+	/// were already current, i.e. skipped.
+	pub(super) const fn up_to_date(raw: NonZeroU64) -> Self {
+		Self {
+			raw,
+			br: None,
+			br_trials: 0,
+			gz: None,
+			zst: None,
+			dcb: None,
+			dcz: None,
+			dict: None,
+			fresh: true,
+			written: Flags::None,
+			removed: Flags::None,
+		}
+	}
+
+	#[cfg(test)]
+	/// # Exhaustive Brotli Savings.
+	pub(super) const fn br_trials(&self) -> u64 { self.br_trials }
+
+	/// # Saved Gzip Size.
+	///
+	/// Return the size of the gzip copy written during this pass, if any.
+	pub(super) const fn gz_saved(&self) -> Option<NonZeroU64> {
+		if self.written.contains(Flags::Gzip) {
+			if let Some(gz) = self.gz { Some(gz) }
+			else { Some(self.raw) }
+		}
+		else { None }
+	}
+
+	#[cfg(test)]
+	/// # Gzip Size.
+	pub(super) const fn gz(&self) -> Option<NonZeroU64> { self.gz }
+
+	#[cfg(test)]
+	/// # Up-to-Date?
+	pub(super) const fn is_up_to_date(&self) -> bool { self.fresh }
+
 	/// # Set Brotli.
 	///
 	/// Set the brotli size if smaller than the original.
 	pub(super) fn set_br(&mut self, br: NonZeroU64) {
+		self.written.set(Flags::Brotli);
 		if br < self.raw { self.br.replace(br); }
 	}
 
@@ -63,8 +161,104 @@ impl EncoderTotals {
 	///
 	/// Set the gzip size if smaller than the original.
 	pub(super) fn set_gz(&mut self, gz: NonZeroU64) {
+		self.written.set(Flags::Gzip);
 		if gz < self.raw { self.gz.replace(gz); }
 	}
+
+	/// # Set Zstd.
+	///
+	/// Set the zstd size if smaller than the original.
+	pub(super) fn set_zst(&mut self, zst: NonZeroU64) {
+		self.written.set(Flags::Zstd);
+		if zst < self.raw { self.zst.replace(zst); }
+	}
+
+	/// # Set Dictionary-Compressed Brotli.
+	///
+	/// Set the dictionary-compressed brotli size if smaller than the original.
+	pub(super) fn set_dcb(&mut self, dcb: NonZeroU64) {
+		self.written.set(Flags::Dcb);
+		if dcb < self.raw { self.dcb.replace(dcb); }
+	}
+
+	/// # Set Dictionary-Compressed Zstd.
+	///
+	/// Set the dictionary-compressed zstd size if smaller than the original.
+	pub(super) fn set_dcz(&mut self, dcz: NonZeroU64) {
+		self.written.set(Flags::Dcz);
+		if dcz < self.raw { self.dcz.replace(dcz); }
+	}
+
+	/// # Set Exhaustive Brotli Savings.
+	pub(super) const fn set_br_trials(&mut self, saved: u64) { self.br_trials = saved; }
+
+	/// # Set Dictionary Hash.
+	pub(super) const fn set_dict(&mut self, hash: [u8; 32]) { self.dict = Some(hash); }
+
+	/// # Set Removed.
+	///
+	/// Note that a previous copy of the given format was removed.
+	pub(super) const fn set_removed(&mut self, kind: Flags) { self.removed.set(kind); }
+
+	/// # Write JSON.
+	///
+	/// Append a JSON object describing this file — its sizes and what became
+	/// of each of the (enabled) encoded copies — to `out`.
+	///
+	/// In dry-run mode, nothing was actually written or removed, so those
+	/// copies are listed as "planned" and "planned_removal" instead.
+	pub(super) fn write_json(&self, path: &str, kinds: Flags, dry_run: bool, out: &mut String) {
+		out.push_str("{\"path\":");
+		json_str(path, out);
+		let _res = write!(out, ",\"raw\":{}", self.raw);
+
+		let encoded = [
+			(Flags::Brotli, "br", self.br),
+			(Flags::Gzip, "gz", self.gz),
+			(Flags::Zstd, "zst", self.zst),
+			(Flags::Dcb, "dcb", self.dcb),
+			(Flags::Dcz, "dcz", self.dcz),
+		];
+		for (kind, label, len) in encoded {
+			if ! kinds.contains(kind) { continue; }
+			let _res = match len {
+				Some(len) => write!(out, ",\"{label}\":{len}"),
+				None => write!(out, ",\"{label}\":null"),
+			};
+			if kind == Flags::Brotli {
+				let _res = write!(out, ",\"br_trials\":{}", self.br_trials);
+			}
+		}
+
+		if kinds.contains_any(Flags::Dcb | Flags::Dcz).is_some() {
+			out.push_str(",\"dict_hash\":");
+			match self.dict {
+				Some(hash) => json_str(&available_dictionary(&hash), out),
+				None => out.push_str("null"),
+			}
+		}
+
+		for (status, set) in [
+			(
+				if dry_run { "planned" } else { "written" },
+				if self.fresh { Flags::None } else { self.written },
+			),
+			("skipped", if self.fresh { kinds & ENCODED } else { Flags::None }),
+			(if dry_run { "planned_removal" } else { "removed" }, self.removed),
+		] {
+			let _res = write!(out, ",\"{status}\":[");
+			let mut first = true;
+			for (kind, label, _) in encoded {
+				if set.contains(kind) {
+					if ! std::mem::take(&mut first) { out.push(','); }
+					let _res = write!(out, "\"{label}\"");
+				}
+			}
+			out.push(']');
+		}
+
+		out.push('}');
+	}
 }
 
 
@@ -81,8 +275,23 @@ pub(super) struct ThreadTotals {
 	/// # Brotli Size.
 	br: u64,
 
+	/// # Exhaustive Brotli Savings.
+	br_trials: u64,
+
 	/// # Gzip Size.
 	gz: u64,
+
+	/// # Zstd Size.
+	zst: u64,
+
+	/// # Dictionary-Compressed Brotli Size.
+	dcb: u64,
+
+	/// # Dictionary-Compressed Zstd Size.
+	dcz: u64,
+
+	/// # Skipped (Up-to-Date) Files.
+	skipped: u64,
 }
 
 impl ThreadTotals {
@@ -93,25 +302,55 @@ impl ThreadTotals {
 		Self {
 			raw: 0,
 			br: 0,
+			br_trials: 0,
 			gz: 0,
+			zst: 0,
+			dcb: 0,
+			dcz: 0,
+			skipped: 0,
 		}
 	}
 
+	/// # Write JSON.
+	///
+	/// Append a JSON object with the grand totals to `out`.
+	pub(super) fn write_json(&self, files: usize, errors: usize, kinds: Flags, out: &mut String) {
+		let _res = write!(out, "{{\"files\":{files},\"raw\":{}", self.raw);
+		for (kind, label, len) in [
+			(Flags::Brotli, "br", self.br),
+			(Flags::Gzip, "gz", self.gz),
+			(Flags::Zstd, "zst", self.zst),
+			(Flags::Dcb, "dcb", self.dcb),
+			(Flags::Dcz, "dcz", self.dcz),
+		] {
+			if kinds.contains(kind) { let _res = write!(out, ",\"{label}\":{len}"); }
+			if kind == Flags::Brotli && kinds.contains(kind) {
+				let _res = write!(out, ",\"br_trials\":{}", self.br_trials);
+			}
+		}
+		let _res = write!(out, ",\"skipped\":{},\"errors\":{errors}}}", self.skipped);
+	}
+
 	/// # Summarize.
 	///
-	/// Print a nice summary of the work done.
-	pub(super) fn summarize(self, kinds: Flags) {
+	/// Print a nice summary of the work done, to STDERR if `stderr`.
+	pub(super) fn summarize(self, kinds: Flags, stderr: bool) {
 		// Print the original raw total with commas in all the right places.
 		let nice_raw = NiceU64::from(self.raw);
 		let nice_len = nice_raw.len();
-		Msg::new(("  Source", AnsiColor::LightMagenta), format!("{nice_raw} bytes"))
-			.with_newline(true)
-			.print();
+		print_msg(
+			&Msg::new(("  Source", AnsiColor::LightMagenta), format!("{nice_raw} bytes"))
+				.with_newline(true),
+			stderr,
+		);
 
 		// Now do the same for each of the (enabled) encoded variants.
-		let encoded: [(u64, &str, bool); 2] = [
+		let encoded: [(u64, &str, bool); 5] = [
 			(self.br,  "  Brotli", kinds.contains(Flags::Brotli)),
 			(self.gz,  "    Gzip", kinds.contains(Flags::Gzip)),
+			(self.zst, "    Zstd", kinds.contains(Flags::Zstd)),
+			(self.dcb, "     DCB", kinds.contains(Flags::Dcb)),
+			(self.dcz, "     DCZ", kinds.contains(Flags::Dcz)),
 		];
 
 		for (total, label, enabled) in encoded {
@@ -129,7 +368,32 @@ impl ThreadTotals {
 				msg.set_suffix(format!(dim!(" (Saved {}.)"), nice_per));
 			}
 
-			msg.print();
+			print_msg(&msg, stderr);
+		}
+
+		// Mention the exhaustive brotli savings, if any.
+		if self.br_trials != 0 && kinds.contains(Flags::Brotli) {
+			print_msg(
+				&Msg::new(("  Trials", AnsiColor::LightMagenta), format!(
+					"{} bytes saved by exhaustive Brotli trials",
+					NiceU64::from(self.br_trials),
+				))
+					.with_newline(true),
+				stderr,
+			);
+		}
+
+		// Mention the skips, if any.
+		if self.skipped != 0 {
+			print_msg(
+				&Msg::new((" Skipped", AnsiColor::LightMagenta), format!(
+					"{} up-to-date {}",
+					NiceU64::from(self.skipped),
+					if self.skipped == 1 { "file" } else { "files" },
+				))
+					.with_newline(true),
+				stderr,
+			);
 		}
 	}
 }
@@ -142,7 +406,12 @@ impl Add for ThreadTotals {
 		Self {
 			raw: self.raw + other.raw,
 			br: self.br + other.br,
+			br_trials: self.br_trials + other.br_trials,
 			gz: self.gz + other.gz,
+			zst: self.zst + other.zst,
+			dcb: self.dcb + other.dcb,
+			dcz: self.dcz + other.dcz,
+			skipped: self.skipped + other.skipped,
 		}
 	}
 }
@@ -153,7 +422,12 @@ impl AddAssign<EncoderTotals> for ThreadTotals {
 		let raw2 = len2.raw.get();
 		self.raw += raw2;
 		self.br += len2.br.map_or(raw2, NonZeroU64::get);
+		self.br_trials += len2.br_trials;
 		self.gz += len2.gz.map_or(raw2, NonZeroU64::get);
+		self.zst += len2.zst.map_or(raw2, NonZeroU64::get);
+		self.dcb += len2.dcb.map_or(raw2, NonZeroU64::get);
+		self.dcz += len2.dcz.map_or(raw2, NonZeroU64::get);
+		if len2.fresh { self.skipped += 1; }
 	}
 }
 
@@ -170,26 +444,57 @@ mod test {
 		assert_eq!(totals.raw, 0);
 		assert_eq!(totals.br, 0);
 		assert_eq!(totals.gz, 0);
+		assert_eq!(totals.zst, 0);
 
 		// Populate encoder totals, then add those to our sum.
 		let mut enc = EncoderTotals::new(NonZeroU64::new(100).unwrap());
 		enc.set_br(NonZeroU64::new(80).unwrap());
+		enc.set_br_trials(3);
 		enc.set_gz(NonZeroU64::new(90).unwrap());
+		enc.set_zst(NonZeroU64::new(85).unwrap());
 		totals += enc;
 		assert_eq!(totals.raw, 100);
 		assert_eq!(totals.br, 80);
+		assert_eq!(totals.br_trials, 3);
 		assert_eq!(totals.gz, 90);
+		assert_eq!(totals.zst, 85);
 
-		// Do it again, but this time leave br/gz set to none.
+		// Do it again, but this time leave br/gz/zst set to none.
 		totals += EncoderTotals::new(NonZeroU64::new(100).unwrap());
 		assert_eq!(totals.raw, 200);
 		assert_eq!(totals.br, 180);
 		assert_eq!(totals.gz, 190);
+		assert_eq!(totals.zst, 185);
 
 		// And verify that ThreadTotal can be added to itself.
 		let totals = totals + totals;
 		assert_eq!(totals.raw, 400);
 		assert_eq!(totals.br, 360);
+		assert_eq!(totals.br_trials, 6);
 		assert_eq!(totals.gz, 380);
+		assert_eq!(totals.zst, 370);
+	}
+
+	#[test]
+	fn t_totals_skipped() {
+		// Encoded totals aren't skips.
+		let mut totals = ThreadTotals::new();
+		let mut enc = EncoderTotals::new(NonZeroU64::new(100).unwrap());
+		enc.set_br(NonZeroU64::new(80).unwrap());
+		totals += enc;
+		totals += EncoderTotals::new(NonZeroU64::new(100).unwrap());
+		assert_eq!(totals.skipped, 0);
+
+		// Up-to-date totals count the same, but also count as skips.
+		totals += EncoderTotals::up_to_date(NonZeroU64::new(100).unwrap());
+		assert_eq!(totals.raw, 300);
+		assert_eq!(totals.br, 280);
+		assert_eq!(totals.skipped, 1);
+
+		// Skips add up too.
+		let totals = totals + totals;
+		assert_eq!(totals.raw, 600);
+		assert_eq!(totals.br, 560);
+		assert_eq!(totals.skipped, 2);
 	}
 }
@@ -0,0 +1,53 @@
+/*!
+# ChannelZ: Check
+
+This module holds the problems turned up by `--check`.
+*/
+
+use crate::{
+	LogKind,
+	PathLog,
+};
+
+
+
+#[derive(Debug, Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+/// # Problem.
+///
+/// The ways an encoded copy can be out of line.
+pub(super) enum Problem {
+	/// # Missing.
+	///
+	/// The source would have produced an encoded copy, but there isn't one.
+	Missing,
+
+	/// # Stale.
+	///
+	/// The encoded copy exists but doesn't decode back to the source.
+	Stale,
+
+	/// # Orphan.
+	///
+	/// The encoded copy exists but the source doesn't (or is empty), or — for
+	/// the dictionary-compressed formats — the dictionary or predecessor
+	/// doesn't.
+	Orphan,
+}
+
+impl LogKind for Problem {
+	/// # As Label.
+	fn as_str(self) -> &'static str {
+		match self {
+			Self::Missing => " Missing",
+			Self::Stale =>   "   Stale",
+			Self::Orphan =>  "  Orphan",
+		}
+	}
+}
+
+
+
+/// # Problems.
+///
+/// This holds the (encoded) paths with problems, along with the problem.
+pub(super) type Problems = PathLog<Problem>;
@@ -0,0 +1,259 @@
+/*!
+# ChannelZ: Configuration
+
+This module handles the (optional) `channelz.toml` project configuration
+file, translating its settings into the equivalent CLI arguments so they can
+be parsed — and overridden — like any other.
+
+Each key is the name of a long flag or option, minus the leading dashes,
+e.g. `br-quality = 9` for `--br-quality 9`. Switches are enabled with `true`
+and left off with `false`, and repeatable options can be given a list.
+
+Named presets go under `[preset.<NAME>]`; their settings replace or follow
+the top-level ones, so take priority. (A preset's `false` thus turns back off
+a switch the top level turns on.)
+
+Settings can be dropped for a given run with `--unset <KEY>`, e.g. to turn
+back off a switch the config turns on.
+*/
+
+use crate::ChannelZError;
+use std::{
+	ffi::OsString,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+use toml::{
+	Table,
+	Value,
+};
+
+
+
+/// # Config File Name.
+const CONFIG_FILE: &str = "channelz.toml";
+
+/// # Off-Limits Keys.
+///
+/// These either make no sense in a config file, or — like the `--stdout`
+/// format choices — only make sense for a one-off run.
+const BAD_KEYS: [&str; 8] = ["br", "config", "gz", "help", "preset", "stdout", "unset", "version"];
+
+/// # Switch Keys.
+///
+/// These are the (allowed) switches, the only settings that take a boolean.
+const SWITCH_KEYS: [&str; 19] = [
+	"br-must-beat-gz", "check", "clean", "clean-only", "decode", "dry-run",
+	"exhaustive", "force", "ignore-files", "incremental", "no-br", "no-gz",
+	"no-preserve", "no-zst", "progress", "prune", "strict", "verify", "zst",
+];
+
+/// # Path Keys.
+///
+/// Relative paths for these settings are resolved against the config file's
+/// directory rather than the working one.
+const PATH_KEYS: [&str; 6] = ["delta-from", "delta-map", "dictionary", "list", "out-dir", "report"];
+
+
+
+/// # Find Config File.
+///
+/// Return the path to the nearest `channelz.toml`, starting with the current
+/// working directory and working upward, if any.
+pub(super) fn find() -> Option<PathBuf> {
+	let mut dir = std::env::current_dir().ok()?;
+	loop {
+		let path = dir.join(CONFIG_FILE);
+		if path.is_file() { return Some(path); }
+		if ! dir.pop() { return None; }
+	}
+}
+
+/// # Load Config File.
+///
+/// Parse the config file, returning its settings — merged with those of
+/// `preset`, if any, minus the `unset` ones — as CLI arguments.
+///
+/// ## Errors
+///
+/// Returns an error if the file is unreadable or invalid, any of its
+/// settings are invalid, or the preset doesn't exist.
+pub(super) fn load(src: &Path, preset: Option<&str>, unset: &[String])
+-> Result<Vec<OsString>, ChannelZError> {
+	let err = || ChannelZError::Config(src.to_string_lossy().into_owned());
+	let raw = std::fs::read_to_string(src).map_err(|_| err())?;
+	let mut table = raw.parse::<Table>().map_err(|_| err())?;
+
+	// Find the preset, if any, and drop the top-level settings it replaces.
+	let presets = table.remove("preset");
+	let extra = match preset {
+		Some(name) => match presets.as_ref().and_then(|p| p.get(name)) {
+			Some(Value::Table(extra)) => extra.clone(),
+			_ => return Err(ChannelZError::Preset(name.to_owned())),
+		},
+		None => Table::new(),
+	};
+	table.retain(|k, _| ! extra.contains_key(k));
+
+	// Drop the settings unset by the CLI.
+	let keep = |(k, _): &(String, Value)| ! unset.contains(k);
+
+	// The preset goes last so its switches — e.g. `no-zst` — win out.
+	let mut out = Vec::new();
+	for (key, value) in table.into_iter().chain(extra).filter(keep) {
+		push_arg(&mut out, &key, value, src)?;
+	}
+	Ok(out)
+}
+
+/// # Bad Key Error.
+///
+/// Return a `ChannelZError::ConfigKey` naming both the key and the config
+/// file it came from.
+pub(super) fn key_error(src: &Path, key: &str) -> ChannelZError {
+	ChannelZError::ConfigKey(format!("{key} (in {})", src.to_string_lossy()))
+}
+
+/// # Push Argument(s).
+///
+/// Add the CLI argument(s) equivalent to the setting to `out`.
+///
+/// Switches must be booleans, and booleans must be switches; `false` adds
+/// nothing.
+fn push_arg(out: &mut Vec<OsString>, key: &str, value: Value, src: &Path)
+-> Result<(), ChannelZError> {
+	let switch = SWITCH_KEYS.contains(&key);
+	if
+		key.is_empty() ||
+		key.starts_with('-') ||
+		BAD_KEYS.contains(&key) ||
+		switch != matches!(value, Value::Boolean(_))
+	{
+		return Err(key_error(src, key));
+	}
+
+	let flag = OsString::from(format!("--{key}"));
+	match value {
+		Value::Boolean(true) => { out.push(flag); },
+		Value::Boolean(false) => {},
+		Value::Integer(n) => {
+			out.push(flag);
+			out.push(OsString::from(n.to_string()));
+		},
+		Value::String(s) => {
+			out.push(flag);
+			if PATH_KEYS.contains(&key) && s != "-" {
+				let dir = src.parent().unwrap_or_else(|| Path::new("."));
+				out.push(dir.join(s).into_os_string());
+			}
+			else { out.push(OsString::from(s)); }
+		},
+		Value::Array(list) => for v in list {
+			if matches!(v, Value::Integer(_) | Value::String(_)) { push_arg(out, key, v, src)?; }
+			else { return Err(key_error(src, key)); }
+		},
+		_ => return Err(key_error(src, key)),
+	}
+
+	Ok(())
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_load() {
+		let dir = std::env::temp_dir();
+		if ! dir.is_dir() { return; }
+		let src = dir.join(format!("channelz-config-{}.toml", std::process::id()));
+		std::fs::write(&src, r#"
+zst = true
+no-br = false
+br-quality = 9
+ext = ["map", "wasm"]
+dictionary = "shared.dict"
+report = "-"
+
+[preset.release]
+br-quality = 11
+gzip-engine = "zopfli"
+no-zst = true
+
+[preset.bad]
+min-size = 1.5
+"#).expect("Unable to write file.");
+
+		// Just the top-level settings.
+		let args = load(&src, None, &[]).expect("Invalid config.");
+		assert_eq!(
+			args,
+			[
+				OsString::from("--br-quality"), OsString::from("9"),
+				OsString::from("--dictionary"), dir.join("shared.dict").into_os_string(),
+				OsString::from("--ext"), OsString::from("map"),
+				OsString::from("--ext"), OsString::from("wasm"),
+				OsString::from("--report"), OsString::from("-"),
+				OsString::from("--zst"),
+			],
+		);
+
+		// With a preset.
+		let args = load(&src, Some("release"), &[]).expect("Invalid config.");
+		assert_eq!(args.len(), 14);
+		assert_eq!(
+			args[9..],
+			[
+				OsString::from("--br-quality"), OsString::from("11"),
+				OsString::from("--gzip-engine"), OsString::from("zopfli"),
+				OsString::from("--no-zst"),
+			],
+		);
+
+		// Unset settings are dropped, preset or not.
+		let unset = [String::from("br-quality"), String::from("ext"), String::from("no-zst")];
+		let args = load(&src, Some("release"), &unset).expect("Invalid config.");
+		assert_eq!(
+			args,
+			[
+				OsString::from("--dictionary"), dir.join("shared.dict").into_os_string(),
+				OsString::from("--report"), OsString::from("-"),
+				OsString::from("--zst"),
+				OsString::from("--gzip-engine"), OsString::from("zopfli"),
+			],
+		);
+
+		// Bad presets.
+		assert_eq!(load(&src, Some("nope"), &[]), Err(ChannelZError::Preset(String::from("nope"))));
+		assert_eq!(load(&src, Some("bad"), &[]), Err(key_error(&src, "min-size")));
+
+		// A preset's false turns off a top-level switch.
+		std::fs::write(&src, "no-br = true\n[preset.br]\nno-br = false\n").expect("Unable to write file.");
+		assert_eq!(load(&src, None, &[]).expect("Invalid config."), [OsString::from("--no-br")]);
+		assert!(load(&src, Some("br"), &[]).expect("Invalid config.").is_empty());
+
+		// Bad keys.
+		for (raw, key) in [
+			("config = \"other.toml\"\n", "config"),
+			("gz = true\n", "gz"),
+			("nope = false\n", "nope"),
+			("br-quality = false\n", "br-quality"),
+			("zst = 1\n", "zst"),
+		] {
+			std::fs::write(&src, raw).expect("Unable to write file.");
+			assert_eq!(load(&src, None, &[]), Err(key_error(&src, key)), "Bad key {key} was accepted.");
+		}
+		let ChannelZError::ConfigKey(s) = key_error(&src, "nope") else { unreachable!(); };
+		assert_eq!(s, format!("nope (in {})", src.to_string_lossy()));
+
+		// Bad TOML.
+		std::fs::write(&src, "zst = \n").expect("Unable to write file.");
+		assert!(matches!(load(&src, None, &[]), Err(ChannelZError::Config(_))), "Invalid TOML was accepted.");
+
+		let _res = std::fs::remove_file(src);
+	}
+}
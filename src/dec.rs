@@ -0,0 +1,150 @@
+/*!
+# ChannelZ: Decoding
+
+These helpers are used to double-check encoded output. Each writes the decoded
+data to `dst` — clearing it first — and returns `false` if the data is invalid
+or would decode to more than `max` bytes.
+*/
+
+use crate::{
+	Dictionary,
+	Flags,
+};
+use std::io::Read;
+
+
+
+/// # Decode.
+///
+/// Decode `src` using the given format. See the individual methods for
+/// details.
+///
+/// The dictionary-compressed formats require a dictionary; without one, they
+/// always fail.
+pub(super) fn decode(
+	kind: Flags,
+	dict: Option<&Dictionary>,
+	src: &[u8],
+	dst: &mut Vec<u8>,
+	max: usize,
+) -> bool {
+	match kind {
+		Flags::Brotli => brotli(src, dst, max),
+		Flags::Gzip => gzip(src, dst, max),
+		Flags::Zstd => zstd(src, dst, max),
+		Flags::Dcb => dict.is_some_and(|d| dcb(d, src, dst, max)),
+		_ => dict.is_some_and(|d| dcz(d, src, dst, max)),
+	}
+}
+
+/// # Decode Brotli.
+pub(super) fn brotli(src: &[u8], dst: &mut Vec<u8>, max: usize) -> bool {
+	dst.clear();
+	let r = brotli::Decompressor::new(src, 4096);
+	read_capped(r, dst, max)
+}
+
+/// # Decode Dictionary-Compressed Brotli.
+///
+/// The data must begin with the header for this particular dictionary.
+pub(super) fn dcb(dict: &Dictionary, src: &[u8], dst: &mut Vec<u8>, max: usize) -> bool {
+	dst.clear();
+	let Some(src) = src.strip_prefix(dict.dcb_header().as_slice()) else { return false; };
+	let r = brotli::Decompressor::new_with_custom_dict(src, 4096, dict.raw().to_vec().into());
+	read_capped(r, dst, max)
+}
+
+/// # Decode Dictionary-Compressed Zstd.
+///
+/// The data must begin with the header for this particular dictionary.
+pub(super) fn dcz(dict: &Dictionary, src: &[u8], dst: &mut Vec<u8>, max: usize) -> bool {
+	dst.clear();
+	let Some(src) = src.strip_prefix(dict.dcz_header().as_slice()) else { return false; };
+	zstd::stream::read::Decoder::with_ref_prefix(src, dict.raw())
+		.is_ok_and(|r| read_capped(r, dst, max))
+}
+
+/// # Decode Gzip.
+///
+/// Libdeflater doesn't stream, so the output buffer is sized according to the
+/// gzip footer (which holds the original length modulo `2^32`).
+pub(super) fn gzip(src: &[u8], dst: &mut Vec<u8>, max: usize) -> bool {
+	dst.clear();
+	let [.., s1, s2, s3, s4] = src else { return false; };
+	let Ok(len) = usize::try_from(u32::from_le_bytes([*s1, *s2, *s3, *s4])) else {
+		return false;
+	};
+	if max < len { return false; }
+
+	dst.resize(len, 0);
+	let mut decoder = libdeflater::Decompressor::new();
+	match decoder.gzip_decompress(src, dst) {
+		Ok(len2) if len2 == len => true,
+		_ => {
+			dst.clear();
+			false
+		},
+	}
+}
+
+/// # Decode Zstd.
+pub(super) fn zstd(src: &[u8], dst: &mut Vec<u8>, max: usize) -> bool {
+	dst.clear();
+	zstd::stream::read::Decoder::with_buffer(src)
+		.is_ok_and(|r| read_capped(r, dst, max))
+}
+
+/// # Read (Capped).
+///
+/// Read everything from `r` into `dst`, so long as it doesn't exceed `max`
+/// bytes.
+fn read_capped<R: Read>(r: R, dst: &mut Vec<u8>, max: usize) -> bool {
+	let cap = u64::try_from(max).map_or(u64::MAX, |n| n.saturating_add(1));
+	if r.take(cap).read_to_end(dst).is_ok() && dst.len() <= max { true }
+	else {
+		dst.clear();
+		false
+	}
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_decode() {
+		const RAW: &[u8] = b"Hello World Hello World Hello World Hello World";
+
+		let mut dst = Vec::new();
+
+		// Gzip.
+		let mut writer = libdeflater::Compressor::new(libdeflater::CompressionLvl::best());
+		let mut enc = vec![0_u8; writer.gzip_compress_bound(RAW.len())];
+		let len = writer.gzip_compress(RAW, &mut enc).expect("Gzip encoding failed.");
+		enc.truncate(len);
+		assert!(gzip(&enc, &mut dst, RAW.len()), "Gzip decoding failed.");
+		assert_eq!(dst, RAW);
+		assert!(! gzip(&enc, &mut dst, RAW.len() - 1), "Gzip cap was ignored.");
+		assert!(! gzip(&enc[..len - 1], &mut dst, RAW.len()), "Truncated gzip decoded.");
+
+		// Zstd.
+		let enc = zstd::bulk::compress(RAW, 19).expect("Zstd encoding failed.");
+		assert!(zstd(&enc, &mut dst, RAW.len()), "Zstd decoding failed.");
+		assert_eq!(dst, RAW);
+		assert!(! zstd(&enc, &mut dst, RAW.len() - 1), "Zstd cap was ignored.");
+		assert!(dst.is_empty(), "Failed decoding should clear the buffer.");
+
+		// Brotli.
+		let mut enc = Vec::new();
+		brotli::enc::BrotliCompress(
+			&mut std::io::Cursor::new(RAW),
+			&mut enc,
+			&brotli::enc::backward_references::BrotliEncoderParams::default(),
+		).expect("Brotli encoding failed.");
+		assert!(brotli(&enc, &mut dst, RAW.len()), "Brotli decoding failed.");
+		assert_eq!(dst, RAW);
+		assert!(! brotli(&enc, &mut dst, RAW.len() - 1), "Brotli cap was ignored.");
+	}
+}
@@ -0,0 +1,339 @@
+/*!
+# ChannelZ: Delta
+
+This module handles delta compression, pairing each source with its
+predecessor from the previous release so the latter can be used as the
+dictionary for the former's `.dcb` and `.dcz` encoded copies.
+*/
+
+use crate::{
+	find_all,
+	OutDir,
+};
+use dowser::Dowser;
+use std::{
+	collections::{
+		hash_map::Entry,
+		HashMap,
+	},
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+
+
+#[derive(Debug, Default)]
+/// # Delta.
+///
+/// This holds the explicitly-mapped predecessors, along with the previous
+/// release (if any) for everything else.
+pub(super) struct Delta {
+	/// # Explicit Pairs.
+	map: HashMap<PathBuf, PathBuf>,
+
+	/// # Previous Release.
+	prev: Option<Previous>,
+}
+
+impl Delta {
+	/// # Load Map.
+	///
+	/// Add the `<NEW>\t<OLD>` pairs from a mapping file, one per line.
+	/// Blank lines are ignored.
+	///
+	/// Returns `None` if the file is unreadable, or any of its lines are
+	/// malformed or reference paths that don't exist.
+	pub(super) fn load_map<P: AsRef<Path>>(&mut self, src: P) -> Option<()> {
+		let raw = std::fs::read_to_string(src).ok()?;
+		for line in raw.lines() {
+			if line.trim().is_empty() { continue; }
+			let (new, old) = line.split_once('\t')?;
+			let new = std::fs::canonicalize(new.trim()).ok()?;
+			let old = std::fs::canonicalize(old.trim()).ok()?;
+			self.map.insert(new, old);
+		}
+		Some(())
+	}
+
+	/// # Load Previous Release.
+	///
+	/// Index the files in the previous release directory by their normalized
+	/// paths. Sources are matched to them by their own normalized paths
+	/// relative to the input `roots`.
+	///
+	/// If more than one file normalizes to the same thing, the most recently
+	/// modified wins.
+	///
+	/// Returns `None` if the directory is invalid.
+	pub(super) fn load_previous<P: AsRef<Path>>(
+		&mut self,
+		dir: P,
+		roots: &[PathBuf],
+		pattern: Pattern,
+	) -> Option<()> {
+		/// # Modification Time.
+		fn mtime(src: &Path) -> Option<std::time::SystemTime> {
+			std::fs::metadata(src).and_then(|m| m.modified()).ok()
+		}
+
+		let dir = dir.as_ref();
+		if ! dir.is_dir() { return None; }
+		let mirror = OutDir::new(dir, roots)?;
+
+		let mut files = HashMap::new();
+		for p in Dowser::from(mirror.dir()).filter(find_all) {
+			match files.entry(pattern.normalize_path(&p)) {
+				Entry::Vacant(e) => { e.insert(p); },
+				Entry::Occupied(mut e) => if mtime(e.get()) < mtime(&p) { e.insert(p); },
+			}
+		}
+
+		self.prev.replace(Previous { mirror, pattern, files });
+		Some(())
+	}
+
+	/// # Previous Release Directory.
+	pub(super) fn dir(&self) -> Option<&Path> {
+		self.prev.as_ref().map(|p| p.mirror.dir())
+	}
+
+	/// # Predecessor.
+	///
+	/// Return the predecessor of `src`, if any.
+	pub(super) fn get(&self, src: &Path) -> Option<&Path> {
+		let old = self.map.get(src)
+			.or_else(|| self.prev.as_ref().and_then(|prev|
+				prev.mirror.map(src).and_then(|dst| prev.files.get(&prev.pattern.normalize_path(&dst)))
+			))?;
+
+		// A file can't be its own predecessor.
+		if old == src { None }
+		else { Some(old) }
+	}
+}
+
+
+
+#[derive(Debug)]
+/// # Previous Release.
+struct Previous {
+	/// # Mirror.
+	///
+	/// This maps sources to where they'd be in the previous release.
+	mirror: OutDir,
+
+	/// # Normalizing Pattern.
+	pattern: Pattern,
+
+	/// # Files (By Normalized Path).
+	files: HashMap<PathBuf, PathBuf>,
+}
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Pattern Token.
+enum Token {
+	/// # Literal Text.
+	Literal(String),
+
+	/// # Name (Anything).
+	Name,
+
+	/// # Hash (Alphanumeric, Dash, Underscore).
+	Hash,
+
+	/// # Extension (Anything But Dots).
+	Ext,
+}
+
+impl Token {
+	/// # Matches Byte?
+	const fn matches(&self, b: u8) -> bool {
+		match self {
+			Self::Literal(_) => false,
+			Self::Name => true,
+			Self::Hash => b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_'),
+			Self::Ext => b != b'.',
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Name-Normalizing Pattern.
+///
+/// This describes how hashed file names are put together, e.g.
+/// `[name].[hash].[ext]`, so that names differing only by hash can be
+/// matched up.
+pub(super) struct Pattern(Vec<Token>);
+
+impl Pattern {
+	/// # Default Pattern.
+	pub(super) const DEFAULT: &str = "[name].[hash].[ext]";
+
+	/// # New.
+	///
+	/// Parse a pattern made up of literal text and the `[name]`, `[hash]`,
+	/// and `[ext]` placeholders, returning `None` unless it has exactly one
+	/// `[hash]`.
+	pub(super) fn new(src: &str) -> Option<Self> {
+		let mut out = Vec::new();
+		let mut lit = String::new();
+		let mut rest = src;
+		while let Some(c) = rest.chars().next() {
+			let token = [("[name]", Token::Name), ("[hash]", Token::Hash), ("[ext]", Token::Ext)]
+				.into_iter()
+				.find_map(|(k, v)| rest.strip_prefix(k).map(|r| (v, r)));
+
+			if let Some((token, r)) = token {
+				if ! lit.is_empty() { out.push(Token::Literal(std::mem::take(&mut lit))); }
+				out.push(token);
+				rest = r;
+			}
+			else {
+				lit.push(c);
+				rest = &rest[c.len_utf8()..];
+			}
+		}
+		if ! lit.is_empty() { out.push(Token::Literal(lit)); }
+
+		if out.iter().filter(|t| matches!(t, Token::Hash)).count() == 1 { Some(Self(out)) }
+		else { None }
+	}
+
+	/// # Normalize File Name.
+	///
+	/// If the name matches the pattern, return it with the hash replaced by
+	/// a `[hash]` placeholder.
+	fn normalize(&self, name: &str) -> Option<String> {
+		let mut span = (0, 0);
+		if match_at(&self.0, name.as_bytes(), 0, &mut span) {
+			Some(format!("{}[hash]{}", &name[..span.0], &name[span.1..]))
+		}
+		else { None }
+	}
+
+	/// # Normalize Path.
+	///
+	/// Same as `Pattern::normalize`, but for the file name of a path. Paths
+	/// that don't match are returned as-is.
+	fn normalize_path(&self, src: &Path) -> PathBuf {
+		src.file_name()
+			.and_then(|n| n.to_str())
+			.and_then(|n| self.normalize(n))
+			.map_or_else(|| src.to_path_buf(), |n| src.with_file_name(n))
+	}
+}
+
+
+
+/// # Match (and Find Hash).
+///
+/// Match `src` from `pos` against the tokens, returning `true` if it matches,
+/// and noting the position of the hash in `span`.
+///
+/// Placeholders are greedy, but will backtrack as needed.
+fn match_at(tokens: &[Token], src: &[u8], pos: usize, span: &mut (usize, usize)) -> bool {
+	let Some((token, rest)) = tokens.split_first() else { return pos == src.len(); };
+
+	if let Token::Literal(lit) = token {
+		return
+			src[pos..].starts_with(lit.as_bytes()) &&
+			match_at(rest, src, pos + lit.len(), span);
+	}
+
+	let max = src[pos..].iter().take_while(|b| token.matches(**b)).count();
+	for end in (pos + 1..=pos + max).rev() {
+		if match_at(rest, src, end, span) {
+			if matches!(token, Token::Hash) { *span = (pos, end); }
+			return true;
+		}
+	}
+
+	false
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_pattern() {
+		assert!(Pattern::new("[name].js").is_none(), "A hash is required.");
+		assert!(Pattern::new("[hash].[hash]").is_none(), "Only one hash is allowed.");
+
+		let pattern = Pattern::new(Pattern::DEFAULT).expect("Invalid pattern.");
+		assert_eq!(pattern.normalize("app.3f2a.js").as_deref(), Some("app.[hash].js"));
+		assert_eq!(pattern.normalize("vendor.min.9c1d.js").as_deref(), Some("vendor.min.[hash].js"));
+		assert_eq!(pattern.normalize("app.js"), None);
+		assert_eq!(
+			pattern.normalize_path(Path::new("/srv/www/app.9c1d.js")),
+			Path::new("/srv/www/app.[hash].js"),
+		);
+		assert_eq!(
+			pattern.normalize_path(Path::new("/srv/www/index.html")),
+			Path::new("/srv/www/index.html"),
+		);
+
+		// Vite-style.
+		let pattern = Pattern::new("[name]-[hash].[ext]").expect("Invalid pattern.");
+		assert_eq!(pattern.normalize("index-BxA3_9zK.js").as_deref(), Some("index-[hash].js"));
+		assert_eq!(pattern.normalize("index.js"), None);
+	}
+
+	#[test]
+	fn t_delta() {
+		let tmp = std::env::temp_dir();
+		if ! tmp.is_dir() { return; }
+		let old_dir = tmp.join("channelz-delta-old");
+		let new_dir = tmp.join("channelz-delta-new");
+		for (dir, name) in [
+			(&old_dir, "app.3f2a.js"),
+			(&old_dir, "app.3f2a.js.br"),
+			(&old_dir, "index.html"),
+			(&new_dir, "app.9c1d.js"),
+			(&new_dir, "index.html"),
+			(&new_dir, "new.1234.js"),
+		] {
+			std::fs::create_dir_all(dir).expect("Unable to create directory.");
+			std::fs::write(dir.join(name), name).expect("Unable to write file.");
+		}
+		let old_dir = std::fs::canonicalize(old_dir).expect("Missing directory.");
+		let new_dir = std::fs::canonicalize(new_dir).expect("Missing directory.");
+
+		let mut delta = Delta::default();
+		delta.load_previous(
+			&old_dir,
+			std::slice::from_ref(&new_dir),
+			Pattern::new(Pattern::DEFAULT).expect("Invalid pattern."),
+		).expect("Invalid previous release.");
+		assert_eq!(delta.dir(), Some(old_dir.as_path()));
+
+		assert_eq!(delta.get(&new_dir.join("app.9c1d.js")), Some(old_dir.join("app.3f2a.js").as_path()));
+		assert_eq!(delta.get(&new_dir.join("index.html")), Some(old_dir.join("index.html").as_path()));
+		assert_eq!(delta.get(&new_dir.join("new.1234.js")), None);
+
+		// Explicit mappings take priority.
+		let map = tmp.join("channelz-delta.tsv");
+		std::fs::write(
+			&map,
+			format!("{}\t{}\n\n", new_dir.join("index.html").display(), old_dir.join("app.3f2a.js").display()),
+		).expect("Unable to write file.");
+		delta.load_map(&map).expect("Invalid map.");
+		assert_eq!(delta.get(&new_dir.join("index.html")), Some(old_dir.join("app.3f2a.js").as_path()));
+
+		// Malformed maps are no good.
+		std::fs::write(&map, "/nowhere\t/nothing\n").expect("Unable to write file.");
+		assert!(Delta::default().load_map(&map).is_none(), "Invalid map was accepted.");
+
+		let _res = std::fs::remove_file(map);
+		let _res = std::fs::remove_dir_all(old_dir);
+		let _res = std::fs::remove_dir_all(new_dir);
+	}
+}
@@ -0,0 +1,136 @@
+/*!
+# ChannelZ: Dictionaries
+
+This module handles the shared dictionaries used for Compression Dictionary
+Transport (RFC 9842), i.e. the `.dcb` and `.dcz` encoded copies.
+*/
+
+use base64::{
+	Engine,
+	engine::general_purpose::STANDARD,
+};
+use sha2::{
+	Digest,
+	Sha256,
+};
+use std::path::Path;
+
+
+
+/// # Dictionary-Compressed Brotli Magic.
+const DCB_MAGIC: [u8; 4] = [0xff, 0x44, 0x43, 0x42];
+
+/// # Dictionary-Compressed Zstd Magic.
+///
+/// This is a zstd skippable frame holding the 32-byte hash that follows it.
+const DCZ_MAGIC: [u8; 8] = [0x5e, 0x2a, 0x4d, 0x18, 0x20, 0x00, 0x00, 0x00];
+
+/// # Dictionary-Compressed Brotli Header Length.
+pub(super) const DCB_HEADER_LEN: usize = DCB_MAGIC.len() + 32;
+
+/// # Dictionary-Compressed Zstd Header Length.
+pub(super) const DCZ_HEADER_LEN: usize = DCZ_MAGIC.len() + 32;
+
+
+
+#[derive(Debug, Clone)]
+/// # Dictionary.
+///
+/// This holds the raw dictionary data along with its SHA-256 hash.
+pub(super) struct Dictionary {
+	/// # Raw Data.
+	raw: Vec<u8>,
+
+	/// # SHA-256 Hash.
+	hash: [u8; 32],
+}
+
+impl Dictionary {
+	/// # From Raw.
+	///
+	/// Returns `None` if the data is empty.
+	pub(super) fn from_raw(raw: Vec<u8>) -> Option<Self> {
+		if raw.is_empty() { None }
+		else {
+			let hash = Sha256::digest(&raw).into();
+			Some(Self { raw, hash })
+		}
+	}
+
+	/// # Load.
+	///
+	/// Read the dictionary from disk, returning `None` if it is unreadable or
+	/// empty.
+	pub(super) fn load<P: AsRef<Path>>(src: P) -> Option<Self> {
+		std::fs::read(src).ok().and_then(Self::from_raw)
+	}
+
+	/// # Raw Data.
+	pub(super) const fn raw(&self) -> &[u8] { self.raw.as_slice() }
+
+	/// # SHA-256 Hash.
+	pub(super) const fn hash(&self) -> [u8; 32] { self.hash }
+
+	/// # Dictionary-Compressed Brotli Header.
+	pub(super) fn dcb_header(&self) -> [u8; DCB_HEADER_LEN] {
+		let mut out = [0_u8; DCB_HEADER_LEN];
+		out[..DCB_MAGIC.len()].copy_from_slice(&DCB_MAGIC);
+		out[DCB_MAGIC.len()..].copy_from_slice(&self.hash);
+		out
+	}
+
+	/// # Dictionary-Compressed Zstd Header.
+	pub(super) fn dcz_header(&self) -> [u8; DCZ_HEADER_LEN] {
+		let mut out = [0_u8; DCZ_HEADER_LEN];
+		out[..DCZ_MAGIC.len()].copy_from_slice(&DCZ_MAGIC);
+		out[DCZ_MAGIC.len()..].copy_from_slice(&self.hash);
+		out
+	}
+}
+
+
+
+/// # Available-Dictionary Value.
+///
+/// Return a dictionary hash as it appears in the HTTP `Available-Dictionary`
+/// header, i.e. a base64-encoded structured field byte sequence.
+pub(super) fn available_dictionary(hash: &[u8; 32]) -> String {
+	format!(":{}:", STANDARD.encode(hash))
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// # Hex.
+	fn hex(src: &[u8]) -> String {
+		use std::fmt::Write;
+		src.iter().fold(String::new(), |mut out, b| {
+			let _res = write!(out, "{b:02x}");
+			out
+		})
+	}
+
+	#[test]
+	fn t_headers() {
+		assert!(Dictionary::from_raw(Vec::new()).is_none(), "Empty dictionaries are invalid.");
+
+		let dict = Dictionary::from_raw(b"abc".to_vec()).expect("Invalid dictionary.");
+		assert_eq!(dict.raw(), b"abc");
+
+		let dcb = dict.dcb_header();
+		assert_eq!(dcb[..4], DCB_MAGIC);
+		assert_eq!(hex(&dcb[4..]), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+		let dcz = dict.dcz_header();
+		assert_eq!(dcz[..8], DCZ_MAGIC);
+		assert_eq!(dcz[8..], dcb[4..]);
+
+		assert_eq!(
+			available_dictionary(&dict.hash()),
+			":ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=:",
+		);
+	}
+}
@@ -5,182 +5,1025 @@
 #![expect(clippy::inline_always, reason = "For performance.")]
 
 use brotli::enc::{
-	backward_references::BrotliEncoderParams,
+	backward_references::{
+		BrotliEncoderMode,
+		BrotliEncoderParams,
+	},
 	BrotliCompress,
+	BrotliCompressCustomIoCustomDict,
+	StandardAlloc,
+};
+use brotli::{
+	IoReaderWrapper,
+	IoWriterWrapper,
 };
 use crate::{
+	dec,
+	Delta,
+	Dictionary,
 	EncoderTotals,
+	ext,
+	FailKind,
+	Failures,
 	Flags,
+	OutDir,
+	Plan,
+	plan::Action,
+	Problem,
+	Problems,
 };
 use libdeflater::{
 	CompressionLvl,
 	Compressor,
 };
 use std::{
-	fs::File,
+	fs::{
+		File,
+		FileTimes,
+		Metadata,
+		Permissions,
+	},
 	io::Cursor,
 	num::NonZeroU64,
+	ops::RangeInclusive,
+	os::unix::ffi::OsStrExt,
 	path::{
 		Path,
 		PathBuf,
 	},
+	time::SystemTime,
 };
 
 
 
+/// # Zstd Compression Level.
+const ZSTD_LEVEL: i32 = 19;
+
+/// # Encodings.
+///
+/// All of the formats, in the order they're (usually) processed.
+const ENCODINGS: [Flags; 5] = [Flags::Gzip, Flags::Zstd, Flags::Brotli, Flags::Dcz, Flags::Dcb];
+
+/// # Brotli Quality Range.
+pub(super) const BR_QUALITY: RangeInclusive<u8> = 0..=11;
+
+/// # Brotli Window Range.
+///
+/// This is the base-2 logarithm of the window size, per RFC 7932.
+pub(super) const BR_WINDOW: RangeInclusive<u8> = 10..=24;
+
+/// # Exhaustive Brotli Windows (Log2).
+///
+/// These are tried in order, stopping early once a window covers the whole
+/// source, as anything bigger would make no difference. Windows larger than
+/// `--br-window` are skipped.
+const BR_TRIAL_WINDOWS: [u8; 3] = [18, 22, 24];
+
+/// # Exhaustive Brotli Block Sizes (Log2).
+///
+/// Zero lets brotli decide for itself.
+const BR_TRIAL_BLOCKS: [i32; 3] = [0, 16, 24];
+
+/// # Gzip Level Range.
+///
+/// Libdeflate goes a bit beyond the usual nine.
+pub(super) const GZ_LEVEL: RangeInclusive<u8> = 1..=12;
+
+
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// # Brotli Mode.
+///
+/// Brotli can be tuned for UTF-8 text or (WOFF 2.0) fonts. By default, the
+/// mode is chosen according to the file type.
+pub(super) enum BrotliMode {
+	#[default]
+	/// # Automatic.
+	///
+	/// Pick one of the others according to the file type.
+	Auto,
+
+	/// # Generic.
+	Generic,
+
+	/// # Text.
+	Text,
+
+	/// # Font.
+	Font,
+}
+
+impl BrotliMode {
+	/// # From Name.
+	///
+	/// Parse the mode from its CLI name.
+	pub(super) fn from_name(name: &str) -> Option<Self> {
+		let name = name.trim();
+		if name.eq_ignore_ascii_case("auto") { Some(Self::Auto) }
+		else if name.eq_ignore_ascii_case("generic") { Some(Self::Generic) }
+		else if name.eq_ignore_ascii_case("text") { Some(Self::Text) }
+		else if name.eq_ignore_ascii_case("font") { Some(Self::Font) }
+		else { None }
+	}
+
+	/// # As Brotli Mode.
+	const fn as_brotli(self) -> BrotliEncoderMode {
+		match self {
+			Self::Auto | Self::Generic => BrotliEncoderMode::BROTLI_MODE_GENERIC,
+			Self::Text => BrotliEncoderMode::BROTLI_MODE_TEXT,
+			Self::Font => BrotliEncoderMode::BROTLI_MODE_FONT,
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+/// # Gzip Engine.
+///
+/// Libdeflate is fast and quite good; Zopfli is much (much) slower, but
+/// usually squeezes out a few more percent.
+pub(super) enum GzipEngine {
+	#[default]
+	/// # Libdeflate.
+	Libdeflate,
+
+	/// # Zopfli (w/ Iterations).
+	Zopfli(NonZeroU64),
+}
+
+impl GzipEngine {
+	/// # Default Zopfli Iterations.
+	pub(super) const ZOPFLI_ITERATIONS: NonZeroU64 = NonZeroU64::new(15).unwrap();
+
+	/// # From Name.
+	///
+	/// Parse the engine from its CLI name, using the given iteration count
+	/// for Zopfli.
+	pub(super) fn from_name(name: &str, iterations: NonZeroU64) -> Option<Self> {
+		let name = name.trim();
+		if name.eq_ignore_ascii_case("libdeflate") { Some(Self::Libdeflate) }
+		else if name.eq_ignore_ascii_case("zopfli") { Some(Self::Zopfli(iterations)) }
+		else { None }
+	}
+}
+
+
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// # Minimum Savings.
+///
+/// Encoded copies are only kept if they're at least this much smaller than
+/// the source.
+pub(super) enum MinSavings {
+	/// # Bytes.
+	Bytes(u64),
+
+	/// # Percent.
+	Percent(u8),
+}
+
+impl Default for MinSavings {
+	fn default() -> Self { Self::Bytes(0) }
+}
+
+impl MinSavings {
+	/// # From Value.
+	///
+	/// Parse a byte count, or a percentage (0–100) if suffixed with "%".
+	pub(super) fn from_value(src: &str) -> Option<Self> {
+		let src = src.trim();
+		if let Some(per) = src.strip_suffix('%') {
+			let per = per.trim_end().parse::<u8>().ok()?;
+			if per <= 100 { Some(Self::Percent(per)) }
+			else { None }
+		}
+		else { src.parse::<u64>().ok().map(Self::Bytes) }
+	}
+
+	/// # Keep?
+	///
+	/// Returns `true` if an encoded copy of `len` bytes saves enough over
+	/// the `raw` source.
+	const fn keep(self, raw: usize, len: usize) -> bool {
+		if raw < len { return false; }
+		let saved = (raw - len) as u64;
+		match self {
+			Self::Bytes(n) => n <= saved,
+			Self::Percent(per) => (raw as u64).saturating_mul(per as u64) <= saved.saturating_mul(100),
+		}
+	}
+}
+
+
+
+#[derive(Debug, Clone, Copy)]
+#[expect(clippy::struct_excessive_bools, reason = "These are independent switches.")]
+/// # Encoder Options.
+///
+/// This holds the user-configurable encoder settings, other than the formats
+/// themselves.
+pub(super) struct EncoderOptions {
+	/// # Brotli Mode.
+	pub(super) br_mode: BrotliMode,
+
+	/// # Brotli Quality.
+	pub(super) br_quality: u8,
+
+	/// # Brotli Window (Log2).
+	pub(super) br_window: u8,
+
+	/// # Brotli Must Beat Gzip?
+	///
+	/// If true, brotli copies are only kept if they're strictly smaller than
+	/// the gzip ones.
+	pub(super) br_must_beat_gz: bool,
+
+	/// # Exhaustive (Brotli)?
+	///
+	/// If true, brotli is run with several different window, block, and mode
+	/// combinations, keeping whichever output is smallest.
+	pub(super) exhaustive: bool,
+
+	/// # Gzip Engine.
+	pub(super) gzip: GzipEngine,
+
+	/// # Gzip Level (Libdeflate).
+	pub(super) gz_level: u8,
+
+	/// # Minimum Savings.
+	pub(super) min_savings: MinSavings,
+
+	/// # Minimum Source Size.
+	///
+	/// Sources smaller than this aren't encoded at all.
+	pub(super) min_size: u64,
+
+	/// # Incremental?
+	///
+	/// If true, sources whose encoded copies all exist and are at least as
+	/// new will be skipped.
+	pub(super) incremental: bool,
+
+	/// # Verify?
+	///
+	/// If true, encoded data is decoded and compared against the source
+	/// before being written.
+	pub(super) verify: bool,
+
+	/// # Preserve Metadata?
+	///
+	/// If true, the source's access/modification times, permissions, and
+	/// (when permitted) ownership are copied to each encoded copy.
+	pub(super) preserve: bool,
+
+	/// # Dry Run?
+	///
+	/// If true, nothing is written or removed; the changes that would have
+	/// been made are added to the encoder's `Plan` instead.
+	pub(super) dry_run: bool,
+}
+
+impl Default for EncoderOptions {
+	fn default() -> Self {
+		Self {
+			br_mode: BrotliMode::Auto,
+			br_quality: *BR_QUALITY.end(),
+			br_window: 22, // Brotli's own default.
+			br_must_beat_gz: false,
+			exhaustive: false,
+			gzip: GzipEngine::default(),
+			gz_level: *GZ_LEVEL.end(),
+			min_savings: MinSavings::Bytes(0),
+			min_size: 0,
+			incremental: false,
+			verify: false,
+			preserve: true,
+			dry_run: false,
+		}
+	}
+}
+
+
+
 /// # Encoder.
 ///
 /// This re-usable (per-thread) structure holds the uncompressed source data,
 /// a buffer for encoding, and output paths for the encoded versions.
-pub(super) struct Encoder {
+pub(super) struct Encoder<'a> {
 	/// # Buffer (Source Data).
 	src: Vec<u8>,
 
+	/// # Source Metadata.
+	src_meta: Option<Metadata>,
+
+	/// # Brotli Mode (Source).
+	br_mode: BrotliMode,
+
 	/// # Buffer (Encoded Data).
 	dst_buf: Vec<u8>,
 
+	/// # Buffer (Trial Data).
+	///
+	/// This is used for the extra brotli passes in exhaustive mode.
+	trial_buf: Vec<u8>,
+
+	/// # Exhaustive Brotli Savings.
+	///
+	/// The number of bytes the last exhaustive brotli run saved over the
+	/// regular settings.
+	br_trials: usize,
+
+	/// # Buffer (Decoded Data).
+	///
+	/// This is used to double-check encoded data.
+	chk_buf: Vec<u8>,
+
 	/// # Output Path (Brotli).
 	dst_br: PathBuf,
 
 	/// # Output Path (Gzip).
 	dst_gz: PathBuf,
 
+	/// # Output Path (Zstd).
+	dst_zst: PathBuf,
+
+	/// # Output Path (Dictionary-Compressed Brotli).
+	dst_dcb: PathBuf,
+
+	/// # Output Path (Dictionary-Compressed Zstd).
+	dst_dcz: PathBuf,
+
 	/// # Formats.
 	kinds: Flags,
+
+	/// # Options.
+	opts: EncoderOptions,
+
+	/// # Output Directory.
+	///
+	/// If set, encoded copies are written to a mirrored tree under this
+	/// directory instead of alongside the sources.
+	out_dir: Option<&'a OutDir>,
+
+	/// # Dictionary.
+	///
+	/// This is required for the dictionary-compressed formats. When delta
+	/// compressing, it is the current source's predecessor.
+	dict: Option<Dictionary>,
+
+	/// # Delta.
+	///
+	/// If set, each source's predecessor is used as its dictionary.
+	delta: Option<&'a Delta>,
+
+	/// # Dry-Run Plan.
+	plan: Plan,
 }
 
-impl Encoder {
+impl<'a> Encoder<'a> {
 	/// # New Instance.
 	///
-	/// Return a new re-usable encoder instance for the given format(s).
-	pub(super) const fn new(kinds: Flags) -> Self {
+	/// Return a new re-usable encoder instance for the given format(s) and
+	/// options.
+	pub(super) const fn new(kinds: Flags, opts: EncoderOptions) -> Self {
 		Self {
 			src: Vec::new(),
+			src_meta: None,
+			br_mode: BrotliMode::Generic,
 			dst_buf: Vec::new(),
+			trial_buf: Vec::new(),
+			br_trials: 0,
+			chk_buf: Vec::new(),
 			dst_br: PathBuf::new(),
 			dst_gz: PathBuf::new(),
+			dst_zst: PathBuf::new(),
+			dst_dcb: PathBuf::new(),
+			dst_dcz: PathBuf::new(),
 			kinds,
+			opts,
+			out_dir: None,
+			dict: None,
+			delta: None,
+			plan: Plan::new(),
 		}
 	}
 
+	#[must_use]
+	/// # With Output Directory.
+	///
+	/// Write the encoded copies to a mirrored tree under the output directory
+	/// instead of alongside the sources.
+	pub(super) const fn with_out_dir(mut self, out_dir: Option<&'a OutDir>) -> Self {
+		self.out_dir = out_dir;
+		self
+	}
+
+	#[must_use]
+	/// # With Dictionary.
+	///
+	/// Set the shared dictionary used for the dictionary-compressed formats.
+	pub(super) fn with_dictionary(mut self, dict: Option<&Dictionary>) -> Self {
+		self.dict = dict.cloned();
+		self
+	}
+
+	#[must_use]
+	/// # With Delta.
+	///
+	/// Use each source's predecessor as the dictionary for the
+	/// dictionary-compressed formats.
+	pub(super) const fn with_delta(mut self, delta: Option<&'a Delta>) -> Self {
+		self.delta = delta;
+		self
+	}
+
+	/// # Take Plan.
+	///
+	/// Return the changes that would have been made in dry-run mode,
+	/// leaving an empty plan in their place.
+	pub(super) fn take_plan(&mut self) -> Plan { std::mem::take(&mut self.plan) }
+
 	/// # Has Brotli?
 	const fn has_br(&self) -> bool { self.kinds.contains(Flags::Brotli) }
 
 	/// # Has Gzip?
 	const fn has_gz(&self) -> bool { self.kinds.contains(Flags::Gzip) }
+
+	/// # Has Zstd?
+	const fn has_zst(&self) -> bool { self.kinds.contains(Flags::Zstd) }
+
+	/// # Has Dictionary-Compressed Brotli?
+	const fn has_dcb(&self) -> bool { self.kinds.contains(Flags::Dcb) }
+
+	/// # Has Dictionary-Compressed Zstd?
+	const fn has_dcz(&self) -> bool { self.kinds.contains(Flags::Dcz) }
 }
 
-impl Encoder {
+impl Encoder<'_> {
 	#[inline(always)]
 	/// # Encode.
 	///
-	/// This method attempts to read `src` and re-encode it with gzip and
-	/// brotli, saving each copy if they offer any improvement, or removing
+	/// This method attempts to read `src` and re-encode it with gzip, zstd,
+	/// and brotli, saving each copy if they offer any improvement, or removing
 	/// previous instances if not.
 	///
 	/// So long as the file is readable and non-empty, this will return the
-	/// uncompressed size and, if brotli and/or gzip copies get saved, their
-	/// sizes too.
+	/// uncompressed size and, if brotli, gzip, and/or zstd copies get saved,
+	/// their sizes too.
 	///
 	/// If an encoding fails, the source size will be returned in its place
 	/// (regardless of how big the encoded version wound up).
-	pub(super) fn encode(&mut self, src: &Path) -> Option<EncoderTotals> {
-		// First, let's update the destination paths.
-		if self.has_br() {
-			src.clone_into(&mut self.dst_br);
-			self.dst_br.as_mut_os_string().push(".br");
-		}
-		if self.has_gz() {
-			src.clone_into(&mut self.dst_gz);
-			self.dst_gz.as_mut_os_string().push(".gz");
+	///
+	/// Read, encode, verification, write, and removal errors, if any, are
+	/// added to `errs`.
+	pub(super) fn encode(&mut self, src: &Path, errs: &mut Failures)
+	-> Option<EncoderTotals> {
+		// First, let's update the destination paths (and dictionary).
+		self.set_paths(src);
+		self.set_dict(src);
+
+		// Skip it if the existing copies are still good.
+		if self.opts.incremental && let Some(len) = self.up_to_date(src) {
+			return Some(len);
 		}
 
 		// Now try to read the source.
-		let Some(len_src) = self.read_source(src) else {
-			self.remove_br();
-			self.remove_gz();
-			return None;
+		let len_src = match self.read_source(src) {
+			Ok(Some(len)) => len,
+			res => {
+				if res.is_err() { errs.push(src.to_path_buf(), FailKind::Read); }
+				for kind in ENCODINGS {
+					if self.kinds.contains(kind) { let _res = self.remove(kind, errs); }
+				}
+				return None;
+			},
 		};
 		let mut len = EncoderTotals::new(len_src);
 
+		// Sources below the minimum size aren't worth encoding, but previous
+		// copies might need clearing out.
+		if len_src.get() < self.opts.min_size {
+			for kind in ENCODINGS {
+				if self.kinds.contains(kind) && self.remove(kind, errs) {
+					len.set_removed(kind);
+				}
+			}
+			return Some(len);
+		}
+
 		// Try to encode it with gzip! This version is done first because it
 		// will likely be bigger, saving brotli the trouble of reallocating.
-		if self.has_gz() {
-			if let Some(l) = self.gzip() { len.set_gz(l); }
-			else { self.remove_gz(); }
-		}
+		if self.has_gz() { self.save(Flags::Gzip, &mut len, errs); }
 
-		// And now do the same with brotli… (Note: this method updates the
-		// destination path accordingly.)
-		if self.has_br() {
-			if let Some(l) = self.brotli() { len.set_br(l); }
-			else { self.remove_br(); }
+		// Zstd usually lands somewhere in between.
+		if self.has_zst() { self.save(Flags::Zstd, &mut len, errs); }
+
+		// And now do the same with brotli.
+		if self.has_br() { self.save(Flags::Brotli, &mut len, errs); }
+
+		// Lastly, the dictionary-compressed variants. When delta compressing,
+		// sources without a predecessor shouldn't have any.
+		if self.delta.is_some() && self.dict.is_none() {
+			for kind in [Flags::Dcz, Flags::Dcb] {
+				if self.kinds.contains(kind) && self.remove(kind, errs) {
+					len.set_removed(kind);
+				}
+			}
+		}
+		else {
+			if self.has_dcz() { self.save(Flags::Dcz, &mut len, errs); }
+			if self.has_dcb() { self.save(Flags::Dcb, &mut len, errs); }
+			if let Some(dict) = &self.dict { len.set_dict(dict.hash()); }
 		}
 
 		// Done!
 		Some(len)
 	}
+
+	/// # Check.
+	///
+	/// This method reads `src` and verifies each of its (enabled) encoded
+	/// copies exist and decode back to the original, without writing or
+	/// removing anything. Problems, if any, are added to `out`.
+	///
+	/// Missing copies are only considered a problem if encoding would have
+	/// actually saved them, i.e. they would have cleared the minimum size and
+	/// savings thresholds.
+	///
+	/// Unreadable and empty sources are silently ignored.
+	pub(super) fn check(&mut self, src: &Path, out: &mut Problems) {
+		self.set_paths(src);
+		self.set_dict(src);
+		if ! matches!(self.read_source(src), Ok(Some(_))) { return; }
+
+		for kind in ENCODINGS {
+			if self.kinds.contains(kind) { self.check_one(kind, out); }
+		}
+	}
+
+	/// # Encode (Stream).
+	///
+	/// Read everything from `r` and encode it with the given format,
+	/// returning the encoded data, or `None` if reading, encoding, or
+	/// verification fail.
+	///
+	/// This is used for `--stdout`; nothing is written anywhere.
+	pub(super) fn encode_reader<R: std::io::Read>(&mut self, mut r: R, kind: Flags) -> Option<&[u8]> {
+		self.br_mode =
+			if matches!(self.opts.br_mode, BrotliMode::Auto) { BrotliMode::Generic }
+			else { self.opts.br_mode };
+		self.src.clear();
+		r.read_to_end(&mut self.src).ok()?;
+
+		self.encode_buf(kind)?;
+		if self.opts.verify && ! self.verify_buf(kind) { return None; }
+		Some(self.dst_buf.as_slice())
+	}
+
+	/// # Estimate.
+	///
+	/// Encode `raw` — as if it were the contents of `src` — with each of the
+	/// enabled formats, returning the sizes without writing anything.
+	///
+	/// This is used to gauge the savings of a freshly-trained dictionary.
+	pub(super) fn estimate(&mut self, src: &Path, raw: &[u8]) -> Option<EncoderTotals> {
+		let mut len = EncoderTotals::new(NonZeroU64::new(raw.len() as u64)?);
+		self.set_br_mode(src);
+		self.src.clear();
+		self.src.extend_from_slice(raw);
+
+		for kind in ENCODINGS {
+			if
+				self.kinds.contains(kind) &&
+				let Some(l) = self.encode_buf(kind) &&
+				l < raw.len() &&
+				let Some(l) = NonZeroU64::new(l as u64)
+			{
+				match kind {
+					Flags::Brotli => { len.set_br(l); },
+					Flags::Gzip => { len.set_gz(l); },
+					Flags::Zstd => { len.set_zst(l); },
+					Flags::Dcb => { len.set_dcb(l); },
+					_ => { len.set_dcz(l); },
+				}
+			}
+		}
+
+		Some(len)
+	}
+
+	#[inline(never)]
+	/// # Check One.
+	///
+	/// Check a single encoded copy for `Encoder::check`.
+	fn check_one(&mut self, kind: Flags, out: &mut Problems) {
+		let dst = self.dst(kind).to_path_buf();
+
+		// Without a dictionary, the dictionary-compressed copies can neither
+		// be checked nor remade.
+		if matches!(kind, Flags::Dcb | Flags::Dcz) && self.dict.is_none() {
+			if dst.exists() { out.push(dst, Problem::Orphan); }
+			return;
+		}
+
+		// If it exists, make sure it decodes to the same thing.
+		if let Ok(enc) = std::fs::read(&dst) {
+			if
+				! dec::decode(kind, self.dict.as_ref(), &enc, &mut self.chk_buf, self.src.len()) ||
+				self.chk_buf != self.src
+			{
+				out.push(dst, Problem::Stale);
+			}
+		}
+		// Otherwise make sure it wouldn't have been saved.
+		else if let Some(len) = self.encode_buf(kind) && self.keep(len) {
+			// Brotli might have lost out to the gzip copy.
+			if
+				kind == Flags::Brotli &&
+				self.has_gz() &&
+				! self.beats_gz(len, std::fs::metadata(&self.dst_gz).ok().and_then(|m| NonZeroU64::new(m.len())))
+			{
+				return;
+			}
+			out.push(dst, Problem::Missing);
+		}
+	}
 }
 
-impl Encoder {
+impl Encoder<'_> {
 	#[inline(always)]
-	/// # Encode With Brotli.
+	/// # Encode and Save.
+	///
+	/// Encode the source with the given format and write it to disk, updating
+	/// `len` with its size. If encoding fails, the output doesn't save enough
+	/// to be worth keeping (or lost to gzip, if required), or the result
+	/// cannot be written, any previous copy is removed instead.
+	///
+	/// If verification is enabled, the encoded data is also decoded and
+	/// compared against the source before writing.
+	fn save(&mut self, kind: Flags, len: &mut EncoderTotals, errs: &mut Failures) {
+		match self.encode_buf(kind) {
+			// We're good so long as the result saved enough.
+			Some(l) if self.keep(l) && (kind != Flags::Brotli || self.beats_gz(l, len.gz_saved())) => {
+				if self.opts.verify && ! self.verify_buf(kind) {
+					errs.push(self.dst(kind).to_path_buf(), FailKind::Verify);
+				}
+				else if let Some(l) = NonZeroU64::new(l as u64) {
+					if self.write(kind) {
+						match kind {
+							Flags::Brotli => {
+								len.set_br(l);
+								len.set_br_trials(self.br_trials as u64);
+							},
+							Flags::Gzip => { len.set_gz(l); },
+							Flags::Zstd => { len.set_zst(l); },
+							Flags::Dcb => { len.set_dcb(l); },
+							_ => { len.set_dcz(l); },
+						}
+						return;
+					}
+					errs.push(self.dst(kind).to_path_buf(), FailKind::Write);
+				}
+			},
+			Some(_) => {},
+			None => { errs.push(self.dst(kind).to_path_buf(), FailKind::Encode); },
+		}
+
+		if self.remove(kind, errs) { len.set_removed(kind); }
+	}
+
+	/// # Write Copy.
+	///
+	/// Write the freshly-encoded data to the format's output path, preserving
+	/// the source's metadata if enabled, returning `false` if that doesn't
+	/// work out.
+	///
+	/// In dry-run mode, the write is added to the plan instead.
+	fn write(&mut self, kind: Flags) -> bool {
+		if self.opts.dry_run {
+			let dst = self.dst(kind).to_path_buf();
+			self.plan.push_write(&dst);
+			true
+		}
+		else if self.mkdir(kind) && write_atomic::write_file(self.dst(kind), &self.dst_buf).is_ok() {
+			if self.opts.preserve { self.preserve(kind); }
+			true
+		}
+		else { false }
+	}
+
+	/// # Worth Keeping?
+	///
+	/// Returns `true` if an encoded copy of `len` bytes clears the minimum
+	/// size and savings thresholds.
+	const fn keep(&self, len: usize) -> bool {
+		self.opts.min_size <= self.src.len() as u64 &&
+		self.opts.min_savings.keep(self.src.len(), len)
+	}
+
+	/// # Beats Gzip?
+	///
+	/// Returns `true` if a brotli copy of `len` bytes is strictly smaller than
+	/// the gzip copy, or if that isn't required (or there is no gzip copy).
+	const fn beats_gz(&self, len: usize, gz: Option<NonZeroU64>) -> bool {
+		! self.opts.br_must_beat_gz ||
+		match gz {
+			Some(gz) => (len as u64) < gz.get(),
+			None => true,
+		}
+	}
+
+	#[inline(never)]
+	/// # Verify Buffer.
+	///
+	/// Decode the freshly-encoded data in `dst_buf` and make sure it matches
+	/// the source, byte-for-byte.
+	fn verify_buf(&mut self, kind: Flags) -> bool {
+		dec::decode(kind, self.dict.as_ref(), &self.dst_buf, &mut self.chk_buf, self.src.len()) &&
+		self.chk_buf == self.src
+	}
+
+	#[inline(never)]
+	/// # Preserve Metadata.
+	///
+	/// Copy the source's access/modification times, permissions, and
+	/// ownership to the format's (freshly-written) output. See
+	/// [`copy_metadata`] for details.
+	fn preserve(&self, kind: Flags) {
+		if let Some(meta) = self.src_meta.as_ref() { copy_metadata(meta, self.dst(kind)); }
+	}
+
+	/// # Make Output Directory.
+	///
+	/// When mirroring to an output directory, make sure the parent directory
+	/// of the format's output path exists, returning `false` if it can't be
+	/// created.
+	fn mkdir(&self, kind: Flags) -> bool {
+		self.out_dir.is_none() ||
+		self.dst(kind).parent().is_none_or(|p| p.is_dir() || std::fs::create_dir_all(p).is_ok())
+	}
+
+	/// # Output Path.
 	///
-	/// This will return `None` if encoding fails, the output winds up _larger_,
-	/// or the result cannot be written to disk, otherwise the length of the
-	/// encoded copy.
-	fn brotli(&mut self) -> Option<NonZeroU64> {
+	/// Return the output path corresponding to the format.
+	fn dst(&self, kind: Flags) -> &Path {
+		match kind {
+			Flags::Brotli => self.dst_br.as_path(),
+			Flags::Gzip => self.dst_gz.as_path(),
+			Flags::Zstd => self.dst_zst.as_path(),
+			Flags::Dcb => self.dst_dcb.as_path(),
+			_ => self.dst_dcz.as_path(),
+		}
+	}
+
+	#[inline(always)]
+	/// # Encode Buffer.
+	///
+	/// Encode the source into `dst_buf` with the given format, returning the
+	/// encoded length, or `None` if the encoder failed.
+	fn encode_buf(&mut self, kind: Flags) -> Option<usize> {
+		match kind {
+			Flags::Brotli => self.brotli_buf(),
+			Flags::Gzip => self.gzip_buf(),
+			Flags::Zstd => self.zstd_buf(),
+			Flags::Dcb => self.dcb_buf(),
+			_ => self.dcz_buf(),
+		}
+	}
+}
+
+impl Encoder<'_> {
+	#[inline(always)]
+	/// # Brotli-Encode Buffer.
+	///
+	/// Encode the source into `dst_buf`, returning the length, or `None` if
+	/// the encoder failed.
+	///
+	/// In exhaustive mode, additional window, block size, and mode
+	/// combinations are tried afterwards, keeping the smallest.
+	fn brotli_buf(&mut self) -> Option<usize> {
 		// Sliceify the source to make life easier.
 		let raw = self.src.as_slice();
 
 		// Reset the buffer and encode!
 		self.dst_buf.clear();
-		let config = BrotliEncoderParams {
+		self.br_trials = 0;
+		let mut config = BrotliEncoderParams {
+			mode: self.br_mode.as_brotli(),
+			quality: i32::from(self.opts.br_quality),
+			lgwin: i32::from(self.opts.br_window),
 			size_hint: raw.len(),
 			..BrotliEncoderParams::default()
 		};
 		let len = BrotliCompress(&mut Cursor::new(raw), &mut self.dst_buf, &config).ok()?;
+		if ! self.opts.exhaustive { return Some(len); }
 
-		// We're good so long as the result didn't get bigger.
-		if len <= raw.len() {
-			let len = NonZeroU64::new(len as u64)?;
+		// Try the alternatives, swapping the buffers whenever one wins.
+		let mut best = len;
+		let modes = [self.br_mode, BrotliMode::Text, BrotliMode::Generic];
+		for (k, mode) in modes.into_iter().enumerate() {
+			if modes[..k].contains(&mode) { continue; }
+			// Never exceed the user's window, but always try it.
+			let windows = BR_TRIAL_WINDOWS.into_iter()
+				.filter(|&w| w < self.opts.br_window)
+				.chain(std::iter::once(self.opts.br_window));
+			for lgwin in windows {
+				for lgblock in BR_TRIAL_BLOCKS {
+					// Skip the combination we already ran.
+					if
+						k == 0 &&
+						lgblock == 0 &&
+						lgwin == self.opts.br_window
+					{
+						continue;
+					}
 
-			// Write the contents and return the length.
-			if write_atomic::write_file(&self.dst_br, &self.dst_buf).is_ok() { Some(len) }
-			else { None }
+					config.mode = mode.as_brotli();
+					config.lgwin = i32::from(lgwin);
+					config.lgblock = lgblock;
+					self.trial_buf.clear();
+					if let Ok(l) = BrotliCompress(&mut Cursor::new(raw), &mut self.trial_buf, &config) && l < best {
+						best = l;
+						std::mem::swap(&mut self.dst_buf, &mut self.trial_buf);
+					}
+				}
+
+				// Bigger windows won't help if this one covers everything.
+				if raw.len() + 16 <= 1_usize << lgwin { break; }
+			}
 		}
-		else { None }
+
+		self.br_trials = len - best;
+		Some(best)
 	}
 
 	#[inline(always)]
-	/// # Encode With Gzip.
+	/// # Gzip-Encode Buffer.
 	///
-	/// This will return `None` if encoding fails, the output winds up _larger_,
-	/// or the result cannot be written to disk, otherwise the length of the
-	/// encoded copy.
-	fn gzip(&mut self) -> Option<NonZeroU64> {
+	/// Encode the source into `dst_buf`, returning the length, or `None` if
+	/// the encoder failed.
+	fn gzip_buf(&mut self) -> Option<usize> {
 		// Sliceify the source to make life easier.
 		let raw = self.src.as_slice();
 
 		// Reset the buffer and encode!
-		let mut writer = Compressor::new(CompressionLvl::best());
-		self.dst_buf.resize(writer.gzip_compress_bound(raw.len()), 0);
-		let len = writer.gzip_compress(raw, &mut self.dst_buf).ok()?;
+		match self.opts.gzip {
+			GzipEngine::Libdeflate => {
+				let lvl = CompressionLvl::new(i32::from(self.opts.gz_level)).ok()?;
+				let mut writer = Compressor::new(lvl);
+				self.dst_buf.resize(writer.gzip_compress_bound(raw.len()), 0);
+				let len = writer.gzip_compress(raw, &mut self.dst_buf).ok()?;
+				self.dst_buf.truncate(len); // Libdeflater doesn't trim to fit.
+				Some(len)
+			},
+			GzipEngine::Zopfli(iteration_count) => {
+				self.dst_buf.clear();
+				let config = zopfli::Options {
+					iteration_count,
+					..zopfli::Options::default()
+				};
+				zopfli::compress(config, zopfli::Format::Gzip, raw, &mut self.dst_buf).ok()?;
+				Some(self.dst_buf.len())
+			},
+		}
+	}
 
-		// We're good so long as the result didn't get bigger.
-		if len <= raw.len() {
-			self.dst_buf.truncate(len); // Libdeflater doesn't trim to fit.
-			let len = NonZeroU64::new(len as u64)?;
+	#[inline(always)]
+	/// # Zstd-Encode Buffer.
+	///
+	/// Encode the source into `dst_buf`, returning the length, or `None` if
+	/// the encoder failed.
+	///
+	/// Note: level 19 is the highest that stays within the 8MiB window limit
+	/// imposed on HTTP `Content-Encoding: zstd` by RFC 9659.
+	fn zstd_buf(&mut self) -> Option<usize> {
+		// Sliceify the source to make life easier.
+		let raw = self.src.as_slice();
 
-			// Write the contents and return the length.
-			if write_atomic::write_file(&self.dst_gz, &self.dst_buf).is_ok() { Some(len) }
-			else { None }
-		}
-		else { None }
+		// Reset the buffer and encode!
+		let mut writer = zstd::bulk::Compressor::new(ZSTD_LEVEL).ok()?;
+		self.dst_buf.clear();
+		self.dst_buf.reserve(zstd::zstd_safe::compress_bound(raw.len()));
+		writer.compress_to_buffer(raw, &mut self.dst_buf).ok()
+	}
+
+	#[inline(always)]
+	/// # Dictionary-Compressed Brotli-Encode Buffer.
+	///
+	/// Encode the source into `dst_buf` using the shared dictionary, prefixed
+	/// with the RFC 9842 header, returning the length, or `None` if the
+	/// encoder failed (or there is no dictionary).
+	fn dcb_buf(&mut self) -> Option<usize> {
+		// Sliceify the source to make life easier.
+		let raw = self.src.as_slice();
+		let dict = self.dict.as_ref()?;
+
+		// Reset the buffer and encode!
+		self.dst_buf.clear();
+		self.dst_buf.extend_from_slice(&dict.dcb_header());
+		let config = BrotliEncoderParams {
+			mode: self.br_mode.as_brotli(),
+			quality: i32::from(self.opts.br_quality),
+			lgwin: i32::from(self.opts.br_window),
+			size_hint: raw.len(),
+			..BrotliEncoderParams::default()
+		};
+		BrotliCompressCustomIoCustomDict(
+			&mut IoReaderWrapper(&mut Cursor::new(raw)),
+			&mut IoWriterWrapper(&mut self.dst_buf),
+			&mut [0; 4096],
+			&mut [0; 4096],
+			&config,
+			StandardAlloc::default(),
+			&mut |_, _, _, _| (),
+			dict.raw(),
+			std::io::Error::from(std::io::ErrorKind::UnexpectedEof),
+		).ok()?;
+
+		Some(self.dst_buf.len())
+	}
+
+	#[inline(always)]
+	/// # Dictionary-Compressed Zstd-Encode Buffer.
+	///
+	/// Encode the source into `dst_buf` using the shared dictionary (as a
+	/// raw prefix), prefixed with the RFC 9842 header, returning the length,
+	/// or `None` if the encoder failed (or there is no dictionary).
+	fn dcz_buf(&mut self) -> Option<usize> {
+		use std::io::Write;
+
+		// Sliceify the source to make life easier.
+		let raw = self.src.as_slice();
+		let dict = self.dict.as_ref()?;
+
+		// Reset the buffer and encode!
+		self.dst_buf.clear();
+		self.dst_buf.extend_from_slice(&dict.dcz_header());
+		let mut writer = zstd::stream::write::Encoder::with_ref_prefix(&mut self.dst_buf, ZSTD_LEVEL, dict.raw()).ok()?;
+		writer.set_pledged_src_size(Some(raw.len() as u64)).ok()?;
+		writer.write_all(raw).ok()?;
+		writer.finish().ok()?;
+
+		Some(self.dst_buf.len())
 	}
 }
 
-impl Encoder {
+impl Encoder<'_> {
+	/// # Set Brotli Mode.
+	///
+	/// Use the mode for `src`'s file type, unless one was explicitly chosen.
+	fn set_br_mode(&mut self, src: &Path) {
+		self.br_mode =
+			if matches!(self.opts.br_mode, BrotliMode::Auto) {
+				ext::brotli_mode(src.as_os_str().as_bytes())
+			}
+			else { self.opts.br_mode };
+	}
+
+	/// # Set Dictionary.
+	///
+	/// When delta compressing, load the predecessor of `src`, if any, to use
+	/// as the dictionary.
+	fn set_dict(&mut self, src: &Path) {
+		if let Some(delta) = self.delta {
+			self.dict = delta.get(src).and_then(Dictionary::load);
+		}
+	}
+
+	/// # Set Destination Paths.
+	///
+	/// Update the (enabled) output paths to match `src`, or its mirror under
+	/// the output directory, if any.
+	///
+	/// The brotli mode is updated too, unless overridden.
+	fn set_paths(&mut self, src: &Path) {
+		self.set_br_mode(src);
+
+		let mirror = self.out_dir.and_then(|o| o.map(src));
+		let src = mirror.as_deref().unwrap_or(src);
+		if self.has_br() {
+			src.clone_into(&mut self.dst_br);
+			self.dst_br.as_mut_os_string().push(".br");
+		}
+		if self.has_gz() {
+			src.clone_into(&mut self.dst_gz);
+			self.dst_gz.as_mut_os_string().push(".gz");
+		}
+		if self.has_zst() {
+			src.clone_into(&mut self.dst_zst);
+			self.dst_zst.as_mut_os_string().push(".zst");
+		}
+		if self.has_dcb() {
+			src.clone_into(&mut self.dst_dcb);
+			self.dst_dcb.as_mut_os_string().push(".dcb");
+		}
+		if self.has_dcz() {
+			src.clone_into(&mut self.dst_dcz);
+			self.dst_dcz.as_mut_os_string().push(".dcz");
+		}
+	}
+
 	#[expect(clippy::cast_possible_truncation, reason = "False positive.")]
 	#[inline(always)]
 	/// # Read Source.
@@ -190,56 +1033,136 @@ impl Encoder {
 	///
 	/// If everything works and the file is non-empty, its size is returned,
 	/// otherwise `None`.
-	fn read_source(&mut self, raw: &Path) -> Option<NonZeroU64> {
+	///
+	/// ## Errors
+	///
+	/// An error is returned if the file cannot be opened or read.
+	fn read_source(&mut self, raw: &Path) -> std::io::Result<Option<NonZeroU64>> {
 		use std::io::Read;
 
-		let Ok(mut file) = File::open(raw) else { return None; };
-		let Ok(meta) = file.metadata() else { return None; };
+		self.src_meta = None;
+		let mut file = File::open(raw)?;
+		let meta = file.metadata()?;
 
 		self.src.clear();
 		let len = meta.len();
-		if len == 0 || self.src.try_reserve_exact(len as usize).is_err() { return None; }
+		self.src_meta = Some(meta);
+		if len == 0 { return Ok(None); }
+		self.src.try_reserve_exact(len as usize).map_err(std::io::Error::other)?;
 
-		if file.read_to_end(&mut self.src).is_ok() {
-			NonZeroU64::new(self.src.len() as u64)
-		}
-		else { None }
+		file.read_to_end(&mut self.src)?;
+		Ok(NonZeroU64::new(self.src.len() as u64))
 	}
 
-	#[cold]
-	/// # Remove Brotli Copy (if it exists)
+	#[inline(never)]
+	/// # Up-to-Date?
 	///
-	/// In cases where encoding can't be run or failed, this method is called
-	/// to remove any previously-generated copy of the encoded content.
-	fn remove_br(&self) {
-		if self.has_br() && self.dst_br.exists() {
-			let _res = std::fs::remove_file(&self.dst_br);
+	/// If every enabled encoded copy exists and is at least as new as the
+	/// source, return their (existing) sizes, otherwise `None`.
+	///
+	/// Note: encoded copies that didn't help the first time around won't have
+	/// been saved, so sources like that will always be re-encoded.
+	fn up_to_date(&self, src: &Path) -> Option<EncoderTotals> {
+		/// # Fresh Copy Size.
+		fn fresh(dst: &Path, mtime: SystemTime) -> Option<NonZeroU64> {
+			let meta = std::fs::metadata(dst).ok()?;
+			if meta.is_file() && mtime <= meta.modified().ok()? {
+				NonZeroU64::new(meta.len())
+			}
+			else { None }
 		}
+
+		let meta = std::fs::metadata(src).ok()?;
+		let mtime = meta.modified().ok()?;
+		let mut len = EncoderTotals::up_to_date(NonZeroU64::new(meta.len())?);
+		if self.has_br() { len.set_br(fresh(&self.dst_br, mtime)?); }
+		if self.has_gz() { len.set_gz(fresh(&self.dst_gz, mtime)?); }
+		if self.has_zst() { len.set_zst(fresh(&self.dst_zst, mtime)?); }
+
+		// When delta compressing, the dictionary-compressed copies also need
+		// to be at least as new as the predecessor, and sources without one
+		// shouldn't have any.
+		let mtime = match self.delta {
+			Some(delta) if self.dict.is_some() => {
+				let pre = delta.get(src).and_then(|p| std::fs::metadata(p).ok()?.modified().ok())?;
+				mtime.max(pre)
+			},
+			Some(_) => {
+				if
+					(self.has_dcb() && self.dst_dcb.exists()) ||
+					(self.has_dcz() && self.dst_dcz.exists())
+				{
+					return None;
+				}
+				return Some(len);
+			},
+			None => mtime,
+		};
+		if self.has_dcb() { len.set_dcb(fresh(&self.dst_dcb, mtime)?); }
+		if self.has_dcz() { len.set_dcz(fresh(&self.dst_dcz, mtime)?); }
+		if let Some(dict) = &self.dict { len.set_dict(dict.hash()); }
+		Some(len)
 	}
 
 	#[cold]
-	/// # Remove Gzip Copy (if it exists)
+	/// # Remove Copy (if it exists)
+	///
+	/// Remove the encoded copy for the given format, if any, noting the
+	/// failure in `errs` if that doesn't work.
 	///
-	/// In cases where encoding can't be run or failed, this method is called
-	/// to remove any previously-generated copy of the encoded content.
-	fn remove_gz(&self) {
-		if self.has_gz() && self.dst_gz.exists() {
-			let _res = std::fs::remove_file(&self.dst_gz);
+	/// Returns `true` if a copy was actually removed.
+	///
+	/// In dry-run mode, the removal is added to the plan instead.
+	fn remove(&mut self, kind: Flags, errs: &mut Failures) -> bool {
+		if ! self.kinds.contains(kind) || ! self.dst(kind).exists() { return false; }
+		if self.opts.dry_run {
+			let dst = self.dst(kind).to_path_buf();
+			self.plan.push(dst, Action::Delete);
+			return true;
 		}
+		let res = std::fs::remove_file(self.dst(kind)).is_ok();
+		if ! res { errs.push(self.dst(kind).to_path_buf(), FailKind::Remove); }
+		res
 	}
 }
 
 
 
+/// # Copy Metadata.
+///
+/// Copy the access/modification times, permissions, and ownership from
+/// `meta` to `dst`.
+///
+/// This is best-effort; ownership in particular usually requires elevated
+/// privileges, so failures are ignored.
+pub(super) fn copy_metadata(meta: &Metadata, dst: &Path) {
+	use std::os::unix::fs::{
+		MetadataExt,
+		PermissionsExt,
+	};
+
+	// Times first, while the file is still ours to open; the source's mode
+	// might not let us afterward.
+	let mut times = FileTimes::new();
+	if let Ok(t) = meta.accessed() { times = times.set_accessed(t); }
+	if let Ok(t) = meta.modified() { times = times.set_modified(t); }
+	let _res = File::open(dst).and_then(|f| f.set_times(times));
+
+	// Ownership next, as changing it can clear setuid/setgid bits.
+	let _res = std::os::unix::fs::chown(dst, Some(meta.uid()), Some(meta.gid()));
+	let _res = std::fs::set_permissions(dst, Permissions::from_mode(meta.mode() & 0o7777));
+}
+
+
+
 #[cfg(test)]
 mod test {
 	use super::*;
+	use crate::dict::available_dictionary;
 	use std::path::PathBuf;
 
 	const RAW: &str = "Björk Guðmundsdóttir OTF (/bjɜːrk/ BYURK, Icelandic: [pjœr̥k ˈkvʏðmʏntsˌtouhtɪr̥] ⓘ; born 21 November 1965) is an Icelandic singer, songwriter, composer, record producer, and actress. Noted for her distinct voice, three-octave vocal range, and sometimes eccentric public persona, she has developed an eclectic musical style over a career spanning four decades, drawing on electronic, pop, experimental, trip hop, classical, and avant-garde music.";
 	const NAME_RAW: &str = "channelz.txt";
-	const NAME_BR: &str = "channelz.txt.br";
-	const NAME_GZ: &str = "channelz.txt.gz";
 
 	/// # Temporary Path.
 	///
@@ -250,6 +1173,55 @@ mod test {
 		else { None }
 	}
 
+	/// # Test Source.
+	///
+	/// An uncompressed source saved to the temporary directory, along with
+	/// the paths its encoded copies would have. Everything is removed on drop.
+	struct TestSource {
+		src: PathBuf,
+		br: PathBuf,
+		gz: PathBuf,
+		zst: PathBuf,
+		dcb: PathBuf,
+		dcz: PathBuf,
+	}
+
+	impl Drop for TestSource {
+		fn drop(&mut self) {
+			for path in [&self.src, &self.br, &self.gz, &self.zst, &self.dcb, &self.dcz] {
+				let _res = std::fs::remove_file(path);
+			}
+		}
+	}
+
+	impl TestSource {
+		/// # New.
+		///
+		/// Save `raw` to the temporary directory as `name`, clearing away any
+		/// copies left over from earlier runs. Returns `None` if there is no
+		/// temporary directory.
+		fn new(name: &str, raw: &[u8]) -> Option<Self> {
+			let src = tmp_path()?.with_file_name(name);
+			write_atomic::write_file(&src, raw).expect("Unable to save source file.");
+			let src = std::fs::canonicalize(src).expect("Unable to canonicalize source.");
+			let copy = |ext: &str| {
+				let mut path = src.clone();
+				path.as_mut_os_string().push(ext);
+				let _res = std::fs::remove_file(&path);
+				path
+			};
+
+			Some(Self {
+				br: copy(".br"),
+				gz: copy(".gz"),
+				zst: copy(".zst"),
+				dcb: copy(".dcb"),
+				dcz: copy(".dcz"),
+				src,
+			})
+		}
+	}
+
 	/// # Decode Brotli.
 	fn decode_brotli(src: &Path) {
 		use std::io::Read;
@@ -292,44 +1264,600 @@ mod test {
 		assert_eq!(dec, RAW, "Gzip enc/dec doesn't match input.");
 	}
 
+	/// # Decode Zstd.
+	fn decode_zstd(src: &Path) {
+		// Load the encoded content.
+		let enc = std::fs::read(src).expect("Missing zstd copy.");
+
+		// Decode it.
+		let dec = zstd::bulk::decompress(&enc, RAW.len())
+			.expect("Zstd decoding failed.");
+		let dec = String::from_utf8(dec)
+			.expect("Zstd decoding is invalid UTF-8.");
+
+		assert_eq!(dec, RAW, "Zstd enc/dec doesn't match input.");
+	}
+
 	#[test]
 	fn t_encode() {
 		// Save an uncompressed source to work with.
-		let Some(src) = tmp_path() else { return; };
-		let src_br = src.with_file_name(NAME_BR);
-		let src_gz = src.with_file_name(NAME_GZ);
-		write_atomic::write_file(&src, RAW.as_bytes()).expect("Unable to save source file.");
+		let Some(t) = TestSource::new(NAME_RAW, RAW.as_bytes()) else { return; };
 
 		// Encode it!
-		let mut encoder = Encoder::new(Flags::All);
-		encoder.encode(&src).expect("Encoding failed!");
+		let mut encoder = Encoder::new(Flags::All, EncoderOptions::default());
+		encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
 
 		// Check the paths.
-		assert_eq!(src_br, encoder.dst_br);
-		assert_eq!(src_gz, encoder.dst_gz);
+		assert_eq!(t.br, encoder.dst_br);
+		assert_eq!(t.gz, encoder.dst_gz);
+		assert_eq!(t.zst, encoder.dst_zst);
+
+		// Decode the encoded copies and compare them to the original.
+		decode_brotli(&t.br);
+		decode_gzip(&t.gz);
+		decode_zstd(&t.zst);
+	}
+
+	#[test]
+	fn t_encode_zopfli() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-zopfli.txt", RAW.as_bytes()) else { return; };
+
+		// Encode it!
+		let mut encoder = Encoder::new(
+			Flags::Gzip,
+			EncoderOptions {
+				gzip: GzipEngine::Zopfli(GzipEngine::ZOPFLI_ITERATIONS),
+				..EncoderOptions::default()
+			},
+		);
+		encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		assert_eq!(t.gz, encoder.dst_gz);
+
+		// Decode and compare.
+		decode_gzip(&t.gz);
+	}
+
+	#[test]
+	fn t_encode_verify() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-verify.txt", RAW.as_bytes()) else { return; };
 
-		// Decode both encoded copies and compare them to the original.
-		decode_brotli(&src_br);
-		decode_gzip(&src_gz);
+		// Encode it, checking along the way.
+		let opts = EncoderOptions { verify: true, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::All, opts);
+		let mut errs = Failures::new();
+		encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(errs.is_empty(), "Verification failed.");
+
+		// The copies should all be there.
+		decode_brotli(&t.br);
+		decode_gzip(&t.gz);
+		decode_zstd(&t.zst);
+	}
+
+	#[test]
+	fn t_encode_failures() {
+		let Some(src) = tmp_path() else { return; };
+		let src = src.with_file_name("channelz-missing.txt");
+		let _res = std::fs::remove_file(&src);
+
+		// Missing sources can't be read.
+		let mut encoder = Encoder::new(Flags::Gzip, EncoderOptions::default());
+		let mut errs = Failures::new();
+		assert!(encoder.encode(&src, &mut errs).is_none());
+		assert_eq!(errs.as_slice(), &[(src.clone(), FailKind::Read)]);
+
+		// Empty sources are skipped, but not an error.
+		write_atomic::write_file(&src, b"").expect("Unable to save source file.");
+		let mut errs = Failures::new();
+		assert!(encoder.encode(&src, &mut errs).is_none());
+		assert!(errs.is_empty(), "Empty files shouldn't be errors.");
 
 		// Clean up.
 		let _res = std::fs::remove_file(&src);
-		let _res = std::fs::remove_file(&src_br);
-		let _res = std::fs::remove_file(&src_gz);
+	}
+
+	#[test]
+	fn t_encode_out_dir() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-out-dir.txt", RAW.as_bytes()) else { return; };
+
+		// Mirror it into a new directory.
+		let dir = t.src.with_file_name("channelz-out-dir");
+		let _res = std::fs::remove_dir_all(&dir);
+		let out = OutDir::new(&dir, std::slice::from_ref(&t.src)).expect("Invalid out dir.");
+		let mut encoder = Encoder::new(Flags::Gzip, EncoderOptions::default())
+			.with_out_dir(Some(&out));
+		let mut errs = Failures::new();
+		encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(errs.is_empty(), "Mirrored encoding failed.");
+
+		// The copy should be in the mirror, not alongside the source.
+		let dst = out.dir().join("channelz-out-dir.txt.gz");
+		assert_eq!(encoder.dst_gz, dst);
+		decode_gzip(&dst);
+		assert!(! t.gz.exists());
+
+		// Clean up.
+		let _res = std::fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn t_encode_preserve() {
+		use std::os::unix::fs::PermissionsExt;
+
+		// Save an uncompressed source with some distinctive metadata.
+		let Some(t) = TestSource::new("channelz-preserve.txt", RAW.as_bytes()) else { return; };
+		let past = SystemTime::UNIX_EPOCH + std::time::Duration::from_hours(24 * 365 * 20);
+		File::options().write(true).open(&t.src)
+			.and_then(|f| f.set_modified(past))
+			.expect("Unable to touch source file.");
+		std::fs::set_permissions(&t.src, Permissions::from_mode(0o640))
+			.expect("Unable to set permissions.");
+
+		// The metadata should be copied by default.
+		let mut encoder = Encoder::new(Flags::Gzip, EncoderOptions::default());
+		encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		let meta = std::fs::metadata(&t.gz).expect("Missing gzip copy.");
+		assert_eq!(meta.modified().ok(), Some(past));
+		assert_eq!(meta.permissions().mode() & 0o777, 0o640);
+
+		// But not if disabled.
+		let opts = EncoderOptions { preserve: false, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::Gzip, opts);
+		encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		let meta = std::fs::metadata(&t.gz).expect("Missing gzip copy.");
+		assert_ne!(meta.modified().ok(), Some(past));
+
+		// Read-only sources shouldn't keep the times from being copied.
+		std::fs::set_permissions(&t.src, Permissions::from_mode(0o444))
+			.expect("Unable to set permissions.");
+		let mut encoder = Encoder::new(Flags::Gzip, EncoderOptions::default());
+		encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		let meta = std::fs::metadata(&t.gz).expect("Missing gzip copy.");
+		assert_eq!(meta.modified().ok(), Some(past));
+		assert_eq!(meta.permissions().mode() & 0o777, 0o444);
+	}
+
+	#[test]
+	fn t_encode_dry_run() {
+		// Save an uncompressed source, along with an old gzip copy.
+		let Some(t) = TestSource::new("channelz-dry-run.txt", RAW.as_bytes()) else { return; };
+		write_atomic::write_file(&t.gz, b"old").expect("Unable to save gzip copy.");
+
+		// The sizes should be the same as usual, but nothing should change.
+		let opts = EncoderOptions { dry_run: true, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::Brotli | Flags::Gzip, opts);
+		let len = encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		assert!(len.gz().is_some(), "Missing gzip size.");
+		assert!(! t.br.exists(), "The brotli copy shouldn't have been written.");
+		assert_eq!(std::fs::read(&t.gz).ok().as_deref(), Some(&b"old"[..]));
+		assert_eq!(
+			encoder.take_plan().as_slice(),
+			[(t.gz.clone(), Action::Replace), (t.br.clone(), Action::Write)],
+		);
+
+		// Same for removals.
+		let opts = EncoderOptions { dry_run: true, min_size: u64::MAX, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::Brotli | Flags::Gzip, opts);
+		encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		assert!(t.gz.exists(), "The gzip copy shouldn't have been removed.");
+		assert_eq!(encoder.take_plan().as_slice(), [(t.gz.clone(), Action::Delete)]);
+		assert!(encoder.take_plan().as_slice().is_empty(), "The plan wasn't taken.");
+	}
+
+	#[test]
+	fn t_encode_incremental() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-incremental.txt", RAW.as_bytes()) else { return; };
+
+		// The first pass has to encode.
+		let opts = EncoderOptions { incremental: true, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::Gzip, opts);
+		let len = encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		assert!(! len.is_up_to_date());
+
+		// The second can skip it.
+		let len2 = encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		assert!(len2.is_up_to_date());
+		assert_eq!(len.gz(), len2.gz());
+
+		// Unless the source changes.
+		let future = SystemTime::now() + std::time::Duration::from_mins(1);
+		File::options().write(true).open(&t.src)
+			.and_then(|f| f.set_modified(future))
+			.expect("Unable to touch source file.");
+		let len2 = encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		assert!(! len2.is_up_to_date());
+	}
+
+	#[test]
+	fn t_check() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-check.txt", RAW.as_bytes()) else { return; };
+
+		// Nothing has been encoded yet, so both should be missing.
+		let kinds = Flags::Brotli | Flags::Gzip;
+		let mut encoder = Encoder::new(kinds, EncoderOptions::default());
+		let mut problems = Problems::new();
+		encoder.check(&t.src, &mut problems);
+		assert_eq!(
+			problems.as_slice(),
+			&[(t.gz.clone(), Problem::Missing), (t.br.clone(), Problem::Missing)],
+		);
+
+		// Encode it for real and the problems should go away.
+		encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		let mut problems = Problems::new();
+		encoder.check(&t.src, &mut problems);
+		assert!(problems.is_empty(), "Fresh copies should pass the check.");
+
+		// Change the source and they should be stale.
+		write_atomic::write_file(&t.src, b"Hello World Hello World Hello World")
+			.expect("Unable to save source file.");
+		let mut problems = Problems::new();
+		encoder.check(&t.src, &mut problems);
+		assert_eq!(
+			problems.as_slice(),
+			&[(t.gz.clone(), Problem::Stale), (t.br.clone(), Problem::Stale)],
+		);
+	}
+
+	#[test]
+	fn t_encode_exhaustive() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-exhaustive.txt", RAW.as_bytes()) else { return; };
+
+		// Encode it normally first.
+		let mut encoder = Encoder::new(Flags::Brotli, EncoderOptions::default());
+		let mut errs = Failures::new();
+		let normal = encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		let len_normal = std::fs::metadata(&t.br).expect("Missing brotli copy.").len();
+		assert_eq!(normal.br_trials(), 0);
+
+		// Now exhaustively.
+		let opts = EncoderOptions { exhaustive: true, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::Brotli, opts);
+		let trials = encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		let len_trials = std::fs::metadata(&t.br).expect("Missing brotli copy.").len();
+		assert!(errs.is_empty(), "Encoding failed.");
+
+		// It should never be bigger, and the difference should be noted.
+		assert!(len_trials <= len_normal, "Exhaustive brotli was bigger.");
+		assert_eq!(trials.br_trials(), len_normal - len_trials);
+		decode_brotli(&t.br);
+	}
+
+	#[test]
+	fn t_encode_exhaustive_window() {
+		/// # Brotli Window (Log2).
+		///
+		/// Parse the window size from a brotli stream header, per RFC 7932.
+		const fn brotli_window(enc: &[u8]) -> u8 {
+			let b = enc[0];
+			if b & 1 == 0 { return 16; }
+			let n = (b >> 1) & 7;
+			if n != 0 { return 17 + n; }
+			let m = (b >> 4) & 7;
+			if m == 0 { 17 } else { 8 + m }
+		}
+
+		// A source that repeats every 2000 bytes, so bigger windows help.
+		let mut seed = 0x2545_f491_u32;
+		let block: Vec<u8> = (0..2000).map(|_| {
+			seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+			b'a' + (seed >> 16).to_le_bytes()[0] % 26
+		}).collect();
+		let raw = block.repeat(4);
+
+		// Left to its own devices, a bigger window should win.
+		let opts = EncoderOptions { exhaustive: true, br_window: 10, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::Brotli, EncoderOptions { br_window: 24, ..opts });
+		let enc = encoder.encode_reader(raw.as_slice(), Flags::Brotli)
+			.expect("Stream encoding failed.");
+		assert!(10 < brotli_window(enc), "A bigger window should have won.");
+
+		// But not if the window is capped.
+		let mut encoder = Encoder::new(Flags::Brotli, opts);
+		let enc = encoder.encode_reader(raw.as_slice(), Flags::Brotli)
+			.expect("Stream encoding failed.");
+		assert_eq!(brotli_window(enc), 10, "The window cap was exceeded.");
+	}
+
+	#[test]
+	fn t_encode_thresholds() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-thresholds.txt", RAW.as_bytes()) else { return; };
+
+		// Encode it normally first.
+		let mut encoder = Encoder::new(Flags::Brotli | Flags::Gzip, EncoderOptions::default());
+		let mut errs = Failures::new();
+		encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		let len_br = std::fs::metadata(&t.br).expect("Missing brotli copy.").len();
+		let len_gz = std::fs::metadata(&t.gz).expect("Missing gzip copy.").len();
+		assert!(len_br < len_gz, "Brotli should be smaller than gzip.");
+
+		// Require exactly brotli's savings; the gzip copy should be removed.
+		let opts = EncoderOptions {
+			min_savings: MinSavings::Bytes(RAW.len() as u64 - len_br),
+			..EncoderOptions::default()
+		};
+		let mut encoder = Encoder::new(Flags::Brotli | Flags::Gzip, opts);
+		let len = encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(t.br.exists(), "Brotli copy is missing.");
+		assert!(! t.gz.exists(), "Gzip copy should have been removed.");
+		assert!(len.gz().is_none());
+
+		// Nothing is worth encoding if the source is too small.
+		let opts = EncoderOptions {
+			min_size: RAW.len() as u64 + 1,
+			..EncoderOptions::default()
+		};
+		let mut encoder = Encoder::new(Flags::Brotli | Flags::Gzip, opts);
+		encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(! t.br.exists(), "Brotli copy should have been removed.");
+		assert!(errs.is_empty(), "Encoding failed.");
+	}
+
+	#[test]
+	fn t_encode_br_must_beat_gz() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-beat-gz.svg", RAW.as_bytes()) else { return; };
+
+		// Brotli wins here, so both copies should be kept.
+		let opts = EncoderOptions { br_must_beat_gz: true, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::Brotli | Flags::Gzip, opts);
+		let mut errs = Failures::new();
+		encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(t.br.exists() && t.gz.exists(), "Encoded copies are missing.");
+
+		// Brotli loses to a gzip copy of the same size, or bigger.
+		let br = std::fs::metadata(&t.br).expect("Missing brotli copy.").len();
+		let br_len = usize::try_from(br).expect("Brotli copy is too big.");
+		assert!(encoder.beats_gz(br_len, NonZeroU64::new(br + 1)));
+		assert!(! encoder.beats_gz(br_len, NonZeroU64::new(br)));
+		assert!(! encoder.beats_gz(br_len, NonZeroU64::new(br - 1)));
+		assert!(encoder.beats_gz(br_len, None));
+
+		// Unless the policy is disabled.
+		let encoder = Encoder::new(Flags::Brotli | Flags::Gzip, EncoderOptions::default());
+		assert!(encoder.beats_gz(br_len, NonZeroU64::new(br)));
+	}
+
+	#[test]
+	fn t_encode_br_loses_to_gz() {
+		// Save a source that repeats every 2000 bytes — too far back for a
+		// 1KiB brotli window to see, but not gzip's 32KiB one.
+		let mut seed = 0x2545_f491_u32;
+		let block: Vec<u8> = (0..2000).map(|_| {
+			seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+			b'a' + (seed >> 16).to_le_bytes()[0] % 26
+		}).collect();
+		let Some(t) = TestSource::new("channelz-lose-gz.txt", &block.repeat(8)) else { return; };
+
+		// Pretend there's a brotli copy from an earlier run.
+		write_atomic::write_file(&t.br, b"Stale.").expect("Unable to save brotli copy.");
+
+		// The losing brotli copy should be dropped, and the old one deleted.
+		let kinds = Flags::Brotli | Flags::Gzip;
+		let opts = EncoderOptions { br_must_beat_gz: true, br_window: 10, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(kinds, opts);
+		let mut errs = Failures::new();
+		let len = encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(errs.is_empty(), "Encoding failed.");
+		assert!(t.gz.exists(), "Gzip copy is missing.");
+		assert!(! t.br.exists(), "The losing brotli copy should have been removed.");
+		let mut json = String::new();
+		len.write_json("", kinds, false, &mut json);
+		assert!(json.contains("\"br\":null"), "Brotli should have no size: {json}");
+		assert!(json.contains("\"written\":[\"gz\"]"), "Only gzip should be written: {json}");
+		assert!(json.contains("\"removed\":[\"br\"]"), "Brotli should be removed: {json}");
+
+		// Without the policy, the (bigger) brotli copy is kept.
+		let opts = EncoderOptions { br_window: 10, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(kinds, opts);
+		encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(errs.is_empty(), "Encoding failed.");
+		let br = std::fs::metadata(&t.br).expect("Missing brotli copy.").len();
+		let gz = std::fs::metadata(&t.gz).expect("Missing gzip copy.").len();
+		assert!(gz <= br, "Brotli shouldn't have won this one.");
+	}
+
+	#[test]
+	fn t_encode_dictionary() {
+		// Save an uncompressed source to work with.
+		let Some(t) = TestSource::new("channelz-dictionary.txt", RAW.as_bytes()) else { return; };
+
+		// Use (most of) the source as its own dictionary, for maximum effect.
+		let dict = Dictionary::from_raw(RAW.as_bytes()[8..].to_vec())
+			.expect("Invalid dictionary.");
+
+		// Without the dictionary, these can't be encoded.
+		let mut encoder = Encoder::new(Flags::Dcb | Flags::Dcz, EncoderOptions::default());
+		let mut errs = Failures::new();
+		encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(errs.contains(FailKind::Encode), "Missing dictionary should fail.");
+
+		// With it, they should be tiny.
+		let opts = EncoderOptions { verify: true, ..EncoderOptions::default() };
+		let mut encoder = Encoder::new(Flags::Dcb | Flags::Dcz, opts)
+			.with_dictionary(Some(&dict));
+		let mut errs = Failures::new();
+		encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(errs.is_empty(), "Dictionary encoding failed.");
+
+		let mut dec = Vec::new();
+		for (path, kind) in [(&t.dcb, Flags::Dcb), (&t.dcz, Flags::Dcz)] {
+			let enc = std::fs::read(path).expect("Missing dictionary-compressed copy.");
+			assert!(enc.len() < 100, "Dictionary compression didn't help.");
+			assert!(dec::decode(kind, Some(&dict), &enc, &mut dec, RAW.len()), "Decoding failed.");
+			assert_eq!(dec, RAW.as_bytes());
+		}
+
+		// The headers should be right.
+		let enc = std::fs::read(&t.dcb).expect("Missing dcb copy.");
+		assert!(enc.starts_with(&dict.dcb_header()));
+		let enc = std::fs::read(&t.dcz).expect("Missing dcz copy.");
+		assert!(enc.starts_with(&dict.dcz_header()));
+
+		// Neither should decode with the wrong dictionary.
+		let wrong = Dictionary::from_raw(b"Hello World".to_vec()).expect("Invalid dictionary.");
+		assert!(! dec::decode(Flags::Dcz, Some(&wrong), &enc, &mut dec, RAW.len()));
+		assert!(! dec::decode(Flags::Dcz, None, &enc, &mut dec, RAW.len()));
+	}
+
+	#[test]
+	fn t_encode_delta() {
+		// Save a source, its predecessor, and an orphan without one.
+		let Some(t) = TestSource::new("channelz-delta.9c1d.txt", RAW.as_bytes()) else { return; };
+		let Some(old) = TestSource::new("channelz-delta.3f2a.txt", &RAW.as_bytes()[8..]) else { return; };
+		let Some(new) = TestSource::new("channelz-delta-new.txt", RAW.as_bytes()) else { return; };
+		let map = t.src.with_file_name("channelz-delta-map.txt");
+		write_atomic::write_file(&map, format!("{}\t{}\n", t.src.display(), old.src.display()).as_bytes())
+			.expect("Unable to save map file.");
+
+		let mut delta = Delta::default();
+		delta.load_map(&map).expect("Invalid map.");
+		let dict = Dictionary::load(&old.src).expect("Invalid dictionary.");
+
+		let kinds = Flags::Dcb | Flags::Dcz;
+		let mut encoder = Encoder::new(kinds, EncoderOptions::default())
+			.with_delta(Some(&delta));
+		let mut errs = Failures::new();
+
+		// The source should be encoded with its predecessor, and the
+		// dictionary hash recorded.
+		let len = encoder.encode(&t.src, &mut errs).expect("Encoding failed!");
+		assert!(errs.is_empty(), "Delta encoding failed.");
+		let mut json = String::new();
+		len.write_json("", kinds, false, &mut json);
+		assert!(
+			json.contains(&format!("\"dict_hash\":\"{}\"", available_dictionary(&dict.hash()))),
+			"Missing dictionary hash: {json}",
+		);
+
+		let mut dec = Vec::new();
+		for (path, kind) in [(&t.dcb, Flags::Dcb), (&t.dcz, Flags::Dcz)] {
+			let enc = std::fs::read(path).expect("Missing dictionary-compressed copy.");
+			assert!(enc.len() < 100, "Delta compression didn't help.");
+			assert!(dec::decode(kind, Some(&dict), &enc, &mut dec, RAW.len()), "Decoding failed.");
+			assert_eq!(dec, RAW.as_bytes());
+		}
+
+		// The orphan should be skipped without complaint.
+		let len = encoder.encode(&new.src, &mut errs).expect("Encoding failed!");
+		assert!(errs.is_empty(), "Sources without predecessors should be skipped.");
+		let mut json = String::new();
+		len.write_json("", kinds, false, &mut json);
+		assert!(json.contains("\"dict_hash\":null"), "Unexpected dictionary hash: {json}");
+		assert!(! new.dcb.exists());
+
+		// Clean up.
+		let _res = std::fs::remove_file(map);
+	}
+
+	#[test]
+	fn t_check_delta() {
+		// Save a source and its predecessor.
+		let Some(t) = TestSource::new("channelz-check-delta.txt", RAW.as_bytes()) else { return; };
+		let Some(old) = TestSource::new("channelz-check-delta.old.txt", &RAW.as_bytes()[8..]) else { return; };
+		let map = t.src.with_file_name("channelz-check-delta-map.txt");
+		write_atomic::write_file(&map, format!("{}\t{}\n", t.src.display(), old.src.display()).as_bytes())
+			.expect("Unable to save map file.");
+
+		let mut delta = Delta::default();
+		delta.load_map(&map).expect("Invalid map.");
+		let mut encoder = Encoder::new(Flags::Dcb | Flags::Dcz, EncoderOptions::default())
+			.with_delta(Some(&delta));
+
+		// Fresh copies are fine.
+		encoder.encode(&t.src, &mut Failures::new()).expect("Encoding failed!");
+		let mut problems = Problems::new();
+		encoder.check(&t.src, &mut problems);
+		assert!(problems.is_empty(), "Fresh copies should pass the check.");
+
+		// But without the predecessor, they're orphans, not stale.
+		drop(old);
+		let mut problems = Problems::new();
+		encoder.check(&t.src, &mut problems);
+		assert_eq!(
+			problems.as_slice(),
+			&[(t.dcz.clone(), Problem::Orphan), (t.dcb.clone(), Problem::Orphan)],
+		);
+
+		// Clean up.
+		let _res = std::fs::remove_file(map);
+	}
+
+	#[test]
+	fn t_encode_reader() {
+		let opts = EncoderOptions { verify: true, ..EncoderOptions::default() };
+		let mut dec = Vec::new();
+		for kind in [Flags::Brotli, Flags::Gzip, Flags::Zstd] {
+			let mut encoder = Encoder::new(kind, opts);
+			let enc = encoder.encode_reader(RAW.as_bytes(), kind)
+				.expect("Stream encoding failed.")
+				.to_vec();
+			assert!(enc.len() < RAW.len(), "Stream encoding didn't help.");
+			assert!(dec::decode(kind, None, &enc, &mut dec, RAW.len()), "Decoding failed.");
+			assert_eq!(dec, RAW.as_bytes());
+		}
+	}
+
+	#[test]
+	fn t_min_savings() {
+		assert_eq!(MinSavings::from_value("128"), Some(MinSavings::Bytes(128)));
+		assert_eq!(MinSavings::from_value(" 5% "), Some(MinSavings::Percent(5)));
+		assert_eq!(MinSavings::from_value("101%"), None);
+		assert_eq!(MinSavings::from_value("-1"), None);
+		assert_eq!(MinSavings::from_value("big"), None);
+
+		assert!(MinSavings::Bytes(0).keep(100, 100));
+		assert!(! MinSavings::Bytes(0).keep(100, 101));
+		assert!(MinSavings::Bytes(10).keep(100, 90));
+		assert!(! MinSavings::Bytes(10).keep(100, 91));
+		assert!(MinSavings::Percent(10).keep(100, 90));
+		assert!(! MinSavings::Percent(10).keep(100, 91));
+	}
+
+	#[test]
+	fn t_brotli_mode() {
+		assert_eq!(BrotliMode::from_name("auto"), Some(BrotliMode::Auto));
+		assert_eq!(BrotliMode::from_name("generic"), Some(BrotliMode::Generic));
+		assert_eq!(BrotliMode::from_name("Text"), Some(BrotliMode::Text));
+		assert_eq!(BrotliMode::from_name("FONT"), Some(BrotliMode::Font));
+		assert_eq!(BrotliMode::from_name("utf8"), None);
+	}
+
+	#[test]
+	fn t_gzip_engine() {
+		let iter = NonZeroU64::new(3).expect("Three is not zero.");
+		assert_eq!(GzipEngine::from_name("libdeflate", iter), Some(GzipEngine::Libdeflate));
+		assert_eq!(GzipEngine::from_name("Zopfli", iter), Some(GzipEngine::Zopfli(iter)));
+		assert_eq!(GzipEngine::from_name("gzip", iter), None);
 	}
 
 	#[test]
 	fn t_encode_kinds() {
-		let enc = Encoder::new(Flags::Brotli);
+		let enc = Encoder::new(Flags::Brotli, EncoderOptions::default());
 		assert!(enc.has_br());
 		assert!(! enc.has_gz());
+		assert!(! enc.has_zst());
 
-		let enc = Encoder::new(Flags::Gzip);
+		let enc = Encoder::new(Flags::Gzip, EncoderOptions::default());
 		assert!(! enc.has_br());
 		assert!(enc.has_gz());
+		assert!(! enc.has_zst());
+
+		let enc = Encoder::new(Flags::Zstd, EncoderOptions::default());
+		assert!(! enc.has_br());
+		assert!(! enc.has_gz());
+		assert!(enc.has_zst());
 
-		let enc = Encoder::new(Flags::All);
+		let enc = Encoder::new(Flags::All, EncoderOptions::default());
 		assert!(enc.has_br());
 		assert!(enc.has_gz());
+		assert!(enc.has_zst());
 	}
 }
@@ -24,45 +24,196 @@ const HELP: &str = concat!(
 (M==M=M==M=M==M==M==M==M)
  \=N=N==N=N==N=N==N=NN=/   ", csi!(199), "ChannelZ", ansi!((cornflower_blue) " v", env!("CARGO_PKG_VERSION")), r#"
   \M==M=M==M=M==M===M=/    Fast, recursive, multi-threaded
-   \N=N==N=N==N=NN=N=/     static Brotli and Gzip encoding.
+   \N=N==N=N==N=NN=N=/     static Brotli, Gzip, and Zstd encoding.
     \M==M==M=M==M==M/
      `-------------'
 
 USAGE:
     channelz [FLAGS] [OPTIONS] <PATH(S)>...
+    channelz --stdout <--br|--gz|--zst> [OPTIONS] < <IN> > <OUT>
 
 FLAGS:
-        --clean       Remove all existing *.gz / *.br files (of types ChannelZ
-                      would encode) before starting, unless --no-gz or --no-br
-                      are also set, respectively.
+        --br          With --stdout, encode with Brotli.
+        --br-must-beat-gz
+                      Only keep Brotli-encoded copies that are strictly
+                      smaller than their Gzip counterparts.
+        --check       Verify the encoded copies are all present and correct —
+                      without writing anything — listing any that are
+                      missing, stale, or orphaned, and exiting non-zero if
+                      there are problems.
+        --clean       Remove all existing *.gz / *.br / *.zst files — and
+                      any *.dcb / *.dcz dictionary-compressed ones — (of
+                      types ChannelZ would encode) before starting, unless
+                      --no-gz, --no-br (*.br, *.dcb), or --no-zst (*.zst,
+                      *.dcz) are also set, respectively.
         --clean-only  Same as --clean, but exit immediately afterward.
+        --decode      Restore the originals from their *.br / *.gz (and with
+                      --zst, *.zst) encoded copies instead, skipping any that
+                      already exist.
+        --dry-run     List the encoded copies that would be written, replaced,
+                      or deleted — along with the projected savings — without
+                      actually writing or deleting anything.
+        --exhaustive  Run Brotli several times with different window, block,
+                      and mode settings, keeping whichever output is
+                      smallest. Windows never exceed --br-window. This is
+                      much slower!
         --force       Try to encode ALL files passed to ChannelZ, regardless of
-                      file extension (except those already ending in .br/.gz).
-                      Be careful with this!
+                      file extension (except those already ending in
+                      .br/.gz/.zst). Be careful with this!
+        --gz          With --stdout, encode with Gzip.
     -h, --help        Print help information and exit.
+        --ignore-files
+                      Skip paths matched by .gitignore, .ignore, or
+                      .channelzignore files found under each <PATH> (in
+                      that order of priority, lowest to highest).
+        --incremental Skip files whose encoded copies all exist and are at
+                      least as new as the source.
         --no-br       Skip Brotli encoding.
         --no-gz       Skip Gzip encoding.
+        --no-preserve Don't copy the source's timestamps, permissions, and
+                      ownership to the encoded copies.
+        --no-zst      Skip Zstd encoding. (This is the default.)
     -p, --progress    Show progress bar while minifying.
+        --prune       Remove any *.gz / *.br / *.zst / *.dcb / *.dcz files (of
+                      types ChannelZ would encode) whose sources no longer
+                      exist or are empty before starting, unless --no-gz,
+                      --no-br (*.br, *.dcb), or --no-zst (*.zst, *.dcz) are
+                      also set, respectively.
+        --stdout      Encode STDIN to STDOUT — instead of crawling paths —
+                      with the one format chosen by --br, --gz, or --zst.
+        --strict      Exit with an error if any file could not be read,
+                      encoded, written, or cleaned up. (These are otherwise
+                      just reported.)
+        --verify      Decode each encoded copy and compare it against the
+                      source before saving it. Mismatches are treated as
+                      errors.
     -V, --version     Print version information and exit.
+        --zst         Generate Zstd-encoded copies too. (With --stdout,
+                      encode with Zstd.)
 
 OPTIONS:
+        --br-mode <MODE>
+                      Tune Brotli for "generic", "text", or "font" data.
+                      By default ("auto"), fonts use the font mode, HTML,
+                      CSS, JS, JSON, SVG, and XML use the text mode, and
+                      everything else is generic.
+        --br-quality <NUM>
+                      The Brotli quality level, from 0 (fastest) to 11
+                      (smallest). [default: 11]
+        --br-window <NUM>
+                      The Brotli window size (log2), from 10 to 24.
+                      [default: 22]
+        --config <FILE>
+                      Load settings from this TOML file instead of the
+                      nearest channelz.toml (searching upward from the
+                      working directory). Arguments passed on the command
+                      line take priority.
+        --decode-limit <NUM[K|M|G]>
+                      With --decode, give up on any encoded copy that would
+                      decode to more than this many bytes. [default: 1G]
+        --delta-from <DIR>
+                      Generate dictionary-compressed Brotli (*.dcb) and/or
+                      Zstd (*.dcz) copies too, using each source's
+                      predecessor from this previous release as its
+                      dictionary. Predecessors are matched by relative path,
+                      ignoring hashes in the file names (see
+                      --delta-pattern). The dictionary hashes are recorded in
+                      the --report.
+        --delta-map <FILE>
+                      Same as --delta-from, but pairing sources and
+                      predecessors explicitly, one <NEW><TAB><OLD> per line.
+                      Mapped pairs take priority.
+        --delta-pattern <PATTERN>
+                      How hashed file names are put together for
+                      --delta-from, using [name], [hash], and [ext]
+                      placeholders. [default: [name].[hash].[ext]]
+        --dictionary <FILE>
+                      Generate dictionary-compressed Brotli (*.dcb) and/or
+                      Zstd (*.dcz) copies too — per RFC 9842 — using this
+                      file as the shared dictionary. The former requires
+                      Brotli, the latter --zst.
+        --exclude <GLOB>
+                      Skip paths matching this pattern, e.g. node_modules
+                      or *.min.js. Patterns are matched against the paths
+                      under each <PATH> — at any depth, unless they start
+                      with a "/" — and excluding a directory skips
+                      everything in it. This option can be repeated.
+        --ext <EXT,…> Encode files with these (comma-separated) extensions
+                      in addition to the built-in types. This option can be
+                      repeated.
+        --gzip-engine <ENGINE>
+                      Gzip with "libdeflate" (the default) or "zopfli".
+                      Zopfli is much slower, but its output is usually a
+                      few percent smaller.
+        --gz-level <NUM>
+                      The (libdeflate) Gzip compression level, from 1
+                      (fastest) to 12 (smallest). [default: 12]
     -l, --list <FILE> Read (absolute) file and/or directory paths to compress
                       from this text file — or STDIN if "-" — one entry per
                       line, instead of or in addition to any trailing
                       <PATH(S)>.
+        --min-savings <NUM[%]>
+                      Only keep encoded copies that are at least this many
+                      bytes — or percent, if suffixed with "%" — smaller
+                      than the source. [default: 0]
+        --min-size <NUM>
+                      Don't encode sources smaller than this many bytes.
+                      [default: 0]
+        --no-ext <EXT,…>
+                      Skip files with these (comma-separated) extensions,
+                      even if they'd otherwise be encoded. This option can be
+                      repeated.
+        --out-dir <DIR>
+                      Save the encoded copies to this directory instead of
+                      alongside the originals, mirroring their paths
+                      relative to each input <PATH>. Parent directories are
+                      created as needed, and --clean cleans the mirror. It
+                      is an error for two sources to share a mirrored path,
+                      or for a source (e.g. a symlink target) to lie outside
+                      every input <PATH>.
+        --preset <NAME>
+                      Apply the settings from the config file's
+                      [preset.<NAME>] section too, overriding its top-level
+                      ones.
+        --report <FILE>
+                      Save a JSON report of the run — per-file sizes, which
+                      encoded copies were written, skipped, or removed (or
+                      with --dry-run, planned), any errors, and the grand
+                      totals — to this file, or STDOUT if "-" (in which
+                      case any other output goes to STDERR).
+        --unset <KEY> Ignore this setting from the config file (and preset)
+                      for the run, e.g. --unset no-br to turn back off a
+                      switch it turns on. This option can be repeated.
+        --zopfli-iterations <NUM>
+                      The number of iterations to run when using the Zopfli
+                      gzip engine. More is slower but (sometimes) smaller.
+                      [default: 15]
 
 ARGS:
     <PATH(S)>...      One or more file and/or directory paths to compress
                       and/or (recursively) crawl.
 
+TRAINING:
+    channelz train-dict [OPTIONS] -o <FILE> <PATH(S)>...
+
+    Build a raw shared dictionary — for use with --dictionary — from a sample
+    of the files ChannelZ would encode, and print the estimated savings for
+    every fifth sampled file, which is held out of training. The --ext,
+    --force, -l/--list, and --no-ext options work the same as above.
+
+    -o, --output <FILE>
+                      Save the dictionary to this file.
+        --size <NUM[K|M]>
+                      The dictionary size, from 1K to 16M. [default: 64K]
+
 ---
 
-Note: static copies will only be generated for files with these extensions:
+Note: unless --force or --ext are used, static copies will only be generated
+for files with these extensions:
 
-    appcache; atom; bmp; css; csv; doc(x); eot; geojson; htc; htm(l); ico; ics;
-    js; json; jsonld; manifest; md; mjs; otf; pdf; rdf; rss; svg; ttf; txt;
-    vcard; vcs; vtt; wasm; webmanifest; xhtm(l); xls(x); xml; xsl; y(a)ml
-"#
+"#,
+	include_str!(concat!(env!("OUT_DIR"), "/help-file-types.txt")),
+	"\n",
 );
 
 
@@ -73,14 +224,48 @@ Note: static copies will only be generated for files with these extensions:
 ///
 /// This is the binary's obligatory custom error type.
 pub(super) enum ChannelZError {
+	BrMode(String),
+	BrQuality,
+	BrWindow,
+	Check,
+	Config(String),
+	ConfigKey(String),
+	Decode,
+	DecodeDryRun,
+	DecodeLimit,
+	DeltaDictionary,
+	DeltaFrom(String),
+	DeltaMap(String),
+	DeltaPattern(String),
+	Dictionary(String),
+	DictOutput(String),
+	DictSize,
+	Exclude(String),
+	GzipEngine(String),
+	GzLevel,
 	InvalidCli(String),
+	InvalidExtension(String),
 	Jobserver,
 	Killed,
 	ListFile,
+	MinSavings,
+	MinSize,
+	NoDictOutput,
 	NoEncoders,
 	NoFiles,
+	NoStdout,
+	OutDir(String),
+	OutDirCollision(String),
+	OutDirRoot(String),
+	Preset(String),
 	PrintHelp,
 	PrintVersion,
+	Report(String),
+	Stdout,
+	Stream,
+	Strict,
+	Verify,
+	ZopfliIterations,
 }
 
 impl std::error::Error for ChannelZError {}
@@ -90,7 +275,23 @@ impl fmt::Display for ChannelZError {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let prefix = self.as_str();
 		match self {
-			Self::InvalidCli(s) => write!(
+			Self::BrMode(s) |
+			Self::Config(s) |
+			Self::ConfigKey(s) |
+			Self::DeltaFrom(s) |
+			Self::DeltaMap(s) |
+			Self::DeltaPattern(s) |
+			Self::Dictionary(s) |
+			Self::DictOutput(s) |
+			Self::Exclude(s) |
+			Self::GzipEngine(s) |
+			Self::InvalidCli(s) |
+			Self::InvalidExtension(s) |
+			Self::OutDir(s) |
+			Self::OutDirCollision(s) |
+			Self::OutDirRoot(s) |
+			Self::Preset(s) |
+			Self::Report(s) => write!(
 				f,
 				concat!("{} ", dim!("{}")),
 				prefix,
@@ -105,14 +306,48 @@ impl ChannelZError {
 	/// # As String Slice.
 	pub(super) const fn as_str(&self) -> &'static str {
 		match self {
+			Self::BrMode(_) => "Invalid brotli mode (expected auto, generic, text, or font):",
+			Self::BrQuality => "Brotli quality must be between 0 and 11.",
+			Self::BrWindow => "Brotli window must be between 10 and 24.",
+			Self::Check => "One or more encoded copies are missing, stale, or orphaned.",
+			Self::Config(_) => "Invalid or unreadable config file:",
+			Self::ConfigKey(_) => "Invalid config setting:",
+			Self::Decode => "One or more encoded copies could not be read, decoded, or restored.",
+			Self::DecodeDryRun => "--decode can't be combined with --dry-run.",
+			Self::DecodeLimit => "Decode limit must be a positive number of bytes (e.g. 512M).",
+			Self::DeltaDictionary => "--dictionary can't be combined with --delta-from or --delta-map.",
+			Self::DeltaFrom(_) => "Invalid previous release directory:",
+			Self::DeltaMap(_) => "Invalid delta map:",
+			Self::DeltaPattern(_) => "Invalid delta pattern (expected exactly one [hash]):",
+			Self::Dictionary(_) => "Invalid or empty dictionary:",
+			Self::DictOutput(_) => "Unable to save the dictionary:",
+			Self::DictSize => "Dictionary size must be between 1K and 16M.",
+			Self::Exclude(_) => "Invalid exclude pattern:",
+			Self::GzipEngine(_) => "Invalid gzip engine (expected libdeflate or zopfli):",
+			Self::GzLevel => "Gzip level must be between 1 and 12.",
 			Self::InvalidCli(_) => "Invalid/unknown argument:",
+			Self::InvalidExtension(_) => "Invalid file extension:",
 			Self::Jobserver => "One or more threads terminated early; please try again.",
 			Self::Killed => "The process was aborted early.",
 			Self::ListFile => "Invalid -l/--list text file.",
+			Self::MinSavings => "Minimum savings must be a number of bytes or a percentage (e.g. 5%).",
+			Self::MinSize => "Minimum size must be a number of bytes.",
+			Self::NoDictOutput => "An output file (-o/--output) is required.",
 			Self::NoEncoders => "At least one encoder needs to be enabled.",
 			Self::NoFiles => "No encodeable files were found.",
+			Self::NoStdout => "--br and --gz only apply to --stdout. (Both encoders are otherwise enabled by default.)",
+			Self::OutDir(_) => "Invalid output directory:",
+			Self::OutDirCollision(_) => "These sources would share the same --out-dir path:",
+			Self::OutDirRoot(_) => "This source is outside every <PATH>, so can't be mirrored to --out-dir:",
+			Self::Preset(_) => "Unknown preset:",
 			Self::PrintHelp => HELP,
 			Self::PrintVersion => concat!("ChannelZ v", env!("CARGO_PKG_VERSION")),
+			Self::Report(_) => "Unable to save the report:",
+			Self::Stdout => "--stdout reads from STDIN (not paths), and needs exactly one of --br, --gz, or --zst.",
+			Self::Stream => "Unable to encode STDIN to STDOUT.",
+			Self::Strict => "One or more files could not be read, encoded, written, or cleaned up.",
+			Self::Verify => "One or more encoded copies failed verification and were not saved.",
+			Self::ZopfliIterations => "Zopfli iterations must be a positive integer.",
 		}
 	}
 }
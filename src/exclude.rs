@@ -0,0 +1,365 @@
+/*!
+# ChannelZ: Exclusions
+
+This module handles `--exclude` and `--ignore-files`, which keep unwanted
+paths — `node_modules`, `.git`, vendored bundles, etc. — out of the crawl.
+*/
+
+use crate::{
+	ChannelZError,
+	ext,
+};
+use globset::{
+	Glob,
+	GlobBuilder,
+	GlobSet,
+	GlobSetBuilder,
+};
+use ignore::{
+	gitignore::{
+		Gitignore,
+		GitignoreBuilder,
+	},
+	Match,
+};
+use std::{
+	collections::HashMap,
+	os::unix::ffi::OsStrExt,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+
+
+/// # Ignore Files.
+///
+/// These are checked for in each directory, in order of increasing priority.
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".channelzignore"];
+
+
+
+#[derive(Debug, Clone, Default)]
+/// # Exclusions.
+///
+/// This holds the `--exclude` patterns and, if enabled, the (lazily-loaded)
+/// ignore file rules for each directory encountered.
+pub(super) struct Exclude {
+	/// # Exclude Patterns.
+	globs: Vec<Glob>,
+
+	/// # Compiled Patterns.
+	set: GlobSet,
+
+	/// # Honor Ignore Files?
+	ignore_files: bool,
+
+	/// # Ignore File Rules (By Directory).
+	rules: HashMap<PathBuf, Option<Gitignore>>,
+
+	/// # Input Roots.
+	roots: Vec<PathBuf>,
+}
+
+impl Exclude {
+	/// # Push Pattern.
+	///
+	/// Add an `--exclude` glob. Patterns are matched against paths relative
+	/// to the input root, and can match at any depth unless they start with
+	/// a `/`.
+	///
+	/// ## Errors
+	///
+	/// Returns an error if the pattern is empty or invalid.
+	pub(super) fn push(&mut self, src: &str) -> Result<(), ChannelZError> {
+		let err = || ChannelZError::Exclude(src.to_owned());
+
+		// Trailing slashes don't matter; leading ones anchor.
+		let pat = src.trim().trim_end_matches('/');
+		let pat = match pat.strip_prefix('/') {
+			Some(pat) => pat.to_owned(),
+			None if pat.is_empty() || pat.starts_with("**") => pat.to_owned(),
+			None => format!("**/{pat}"),
+		};
+		if pat.is_empty() { return Err(err()); }
+
+		let glob = GlobBuilder::new(&pat)
+			.literal_separator(true)
+			.build()
+			.map_err(|_| err())?;
+		self.globs.push(glob);
+		Ok(())
+	}
+
+	/// # Honor Ignore Files.
+	///
+	/// Skip paths matched by the `.gitignore`, `.ignore`, or `.channelzignore`
+	/// files in their directories or any parent directory up to the input
+	/// root.
+	pub(super) const fn set_ignore_files(&mut self) { self.ignore_files = true; }
+
+	/// # Finish.
+	///
+	/// Compile the patterns, and note the (canonical) input roots they're
+	/// relative to.
+	///
+	/// ## Errors
+	///
+	/// Returns an error if the patterns can't be compiled.
+	pub(super) fn finish(&mut self, roots: &[PathBuf]) -> Result<(), ChannelZError> {
+		let mut builder = GlobSetBuilder::new();
+		for glob in self.globs.drain(..) { builder.add(glob); }
+		self.set = builder.build()
+			.map_err(|e| ChannelZError::Exclude(e.to_string()))?;
+		roots.clone_into(&mut self.roots);
+		Ok(())
+	}
+
+	/// # Is Excluded?
+	///
+	/// Returns `true` if the (canonical) file path — or any directory between
+	/// it and its input root — has been excluded.
+	///
+	/// Paths under more than one root are only excluded if all of them agree.
+	/// Paths outside every root, e.g. symlink targets, are never excluded.
+	pub(super) fn excludes(&mut self, path: &Path) -> bool {
+		if self.set.is_empty() && ! self.ignore_files { return false; }
+
+		let roots = std::mem::take(&mut self.roots);
+		let mut found = false;
+		let mut out = true;
+		for root in roots.iter().filter(|root| path.starts_with(root)) {
+			found = true;
+			if ! self.excludes_under(root, path) {
+				out = false;
+				break;
+			}
+		}
+		self.roots = roots;
+
+		found && out
+	}
+
+	#[must_use]
+	/// # Mirror.
+	///
+	/// Return a copy of the exclusions relative to `dir` — e.g. the output
+	/// directory — instead of the input roots.
+	pub(super) fn mirror(&self, dir: &Path) -> Self {
+		let mut out = self.clone();
+		out.roots = vec![dir.to_path_buf()];
+		out
+	}
+
+	/// # Is Excluded (Under Root)?
+	///
+	/// Same as `Exclude::excludes`, but for a single `root`.
+	fn excludes_under(&mut self, root: &Path, path: &Path) -> bool {
+		let Ok(rel) = path.strip_prefix(root) else { return false; };
+
+		// Check the directories along the way, outermost first.
+		let mut dir = root.to_path_buf();
+		let mut parts = rel.components();
+		parts.next_back();
+		for part in parts {
+			dir.push(part);
+			if self.matches(root, &dir, true) { return true; }
+		}
+
+		self.matches(root, path, false)
+	}
+
+	/// # Is Excluded (Exactly)?
+	///
+	/// Returns `true` if `path` — a file or directory somewhere under `root`
+	/// — has been excluded, either explicitly or by an ignore file. Encoded
+	/// copies are excluded along with their sources.
+	///
+	/// Only `path` itself is checked, not its parent directories.
+	fn matches(&mut self, root: &Path, path: &Path, is_dir: bool) -> bool {
+		// The root itself is fair game.
+		let Ok(rel) = path.strip_prefix(root) else { return false; };
+		if rel.as_os_str().is_empty() { return false; }
+
+		// Encoded copies go with their sources.
+		let src =
+			if ! is_dir && ext::match_encoded(path.as_os_str().as_bytes()) {
+				Some(path.with_extension(""))
+			}
+			else { None };
+
+		if ! self.set.is_empty() {
+			if self.set.is_match(rel) { return true; }
+			if
+				let Some(src) = src.as_deref() &&
+				let Ok(rel) = src.strip_prefix(root) &&
+				self.set.is_match(rel)
+			{
+				return true;
+			}
+		}
+
+		if self.ignore_files {
+			// The nearest rules win, but nothing above the root counts.
+			for dir in path.ancestors().skip(1).take_while(|dir| dir.starts_with(root)) {
+				if ! self.rules.contains_key(dir) {
+					self.rules.insert(dir.to_path_buf(), load_rules(dir));
+				}
+				let Some(Some(rules)) = self.rules.get(dir) else { continue; };
+				for p in std::iter::once(path).chain(src.as_deref()) {
+					match rules.matched(p, is_dir) {
+						Match::Ignore(_) => return true,
+						Match::Whitelist(_) => return false,
+						Match::None => {},
+					}
+				}
+			}
+		}
+
+		false
+	}
+}
+
+/// # Load Ignore Rules.
+///
+/// Parse the ignore files in `dir`, if any. Unreadable files and invalid
+/// lines are silently skipped, same as `git` does.
+fn load_rules(dir: &Path) -> Option<Gitignore> {
+	let mut builder = GitignoreBuilder::new(dir);
+	let mut found = false;
+	for name in IGNORE_FILES {
+		let path = dir.join(name);
+		if path.is_file() {
+			let _res = builder.add(path);
+			found = true;
+		}
+	}
+
+	if found { builder.build().ok().filter(|g| ! g.is_empty()) }
+	else { None }
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_exclude() {
+		let mut ex = Exclude::default();
+		for pat in ["node_modules/", ".git", "*.min.js", "assets/vendor", "/skip/**"] {
+			assert!(ex.push(pat).is_ok(), "Pattern {pat:?} should be valid.");
+		}
+		assert!(ex.push("").is_err(), "Empty patterns are invalid.");
+		assert!(ex.push("/").is_err(), "Empty patterns are invalid.");
+		assert!(ex.push("[oops").is_err(), "Unclosed classes are invalid.");
+		assert!(ex.finish(&[]).is_ok());
+
+		let root = Path::new("/srv/build/app");
+		for (path, is_dir, expected) in [
+			("node_modules", true, true),
+			("lib/node_modules", true, true),
+			(".git", true, true),
+			("main.min.js", false, true),
+			("main.min.js.gz", false, true),
+			("main.js", false, false),
+			("assets/vendor", true, true),
+			("assets/vendors", true, false),
+			("public/node_modules.js", false, false),
+			("skip/deep/index.html", false, true),
+			("lib/skip/index.html", false, false),
+		] {
+			assert_eq!(
+				ex.matches(root, &root.join(path), is_dir),
+				expected,
+				"Exclusion mismatch for {path}.",
+			);
+		}
+
+		// Only the part under the root counts.
+		let mut ex = Exclude::default();
+		assert!(ex.push("build").is_ok());
+		assert!(ex.finish(&[]).is_ok());
+		assert!(! ex.matches(root, &root.join("main.js"), false));
+		assert!(! ex.matches(root, root, true));
+		assert!(ex.matches(root, &root.join("build"), true));
+
+		// Nothing is excluded by default.
+		let mut ex = Exclude::default();
+		assert!(ex.finish(&[]).is_ok());
+		assert!(! ex.matches(root, &root.join("node_modules"), true));
+
+		// Files go with their directories, relative to each root.
+		let mut ex = Exclude::default();
+		assert!(ex.push("build").is_ok());
+		assert!(ex.push("node_modules").is_ok());
+		assert!(ex.finish(&[root.to_path_buf()]).is_ok());
+		for (path, expected) in [
+			("/srv/build/app/index.html", false),
+			("/srv/build/app/node_modules/pkg/lib.js", true),
+			("/srv/build/app/js/build/app.js", true),
+			("/srv/build/app/js/app.js", false),
+			("/srv/node_modules/lib.js", false),
+		] {
+			assert_eq!(ex.excludes(Path::new(path)), expected, "Exclusion mismatch for {path}.");
+		}
+
+		// Mirrors are relative to their own directory.
+		let mut mirror = ex.mirror(Path::new("/out"));
+		assert!(mirror.excludes(Path::new("/out/build/app.js")));
+		assert!(! mirror.excludes(Path::new("/out/app.js")));
+		assert!(! mirror.excludes(Path::new("/srv/build/app/node_modules/pkg/lib.js")));
+
+		// Overlapping roots have to agree.
+		let mut ex = Exclude::default();
+		assert!(ex.push("build").is_ok());
+		assert!(ex.push("node_modules").is_ok());
+		assert!(ex.finish(&[root.to_path_buf(), root.join("js/build")]).is_ok());
+		assert!(! ex.excludes(Path::new("/srv/build/app/js/build/app.js")));
+		assert!(ex.excludes(Path::new("/srv/build/app/js/build/node_modules/app.js")));
+	}
+
+	#[test]
+	fn t_ignore_files() {
+		let dir = std::env::temp_dir().join(format!("channelz-exclude-{}", std::process::id()));
+		if std::fs::create_dir_all(dir.join("root/sub/dist")).is_err() { return; }
+		let Ok(dir) = std::fs::canonicalize(dir) else { return; };
+		let root = dir.join("root");
+
+		// Rules above the root don't apply.
+		std::fs::write(dir.join(".gitignore"), "*.css\n").expect("Unable to write file.");
+		std::fs::write(root.join(".gitignore"), "dist/\n*.map\n").expect("Unable to write file.");
+		std::fs::write(root.join("sub/.channelzignore"), "!keep.map\nlocal.css\n")
+			.expect("Unable to write file.");
+
+		// Disabled by default.
+		let mut ex = Exclude::default();
+		assert!(ex.finish(&[]).is_ok());
+		assert!(! ex.matches(&root, &root.join("app.map"), false));
+
+		let mut ex = Exclude::default();
+		ex.set_ignore_files();
+		assert!(ex.finish(&[]).is_ok());
+		for (path, is_dir, expected) in [
+			("app.js", false, false),
+			("app.css", false, false),
+			("app.map", false, true),
+			("app.map.br", false, true),
+			("sub/dist", true, true),
+			("sub/local.css", false, true),
+			("sub/keep.map", false, false),
+			("sub/other.map", false, true),
+			("local.css", false, false),
+		] {
+			assert_eq!(
+				ex.matches(&root, &root.join(path), is_dir),
+				expected,
+				"Ignore mismatch for {path}.",
+			);
+		}
+
+		let _res = std::fs::remove_dir_all(&dir);
+	}
+}
@@ -2,18 +2,36 @@
 # ChannelZ: Extensions
 */
 
+use crate::{
+	BrotliMode,
+	ChannelZError,
+	Flags,
+};
 use dowser::Extension;
 
 
 
 /// # Helper: Define Extensions.
+///
+/// The invocation is generated by `build.rs` from its list of supported file
+/// types, each followed by its Brotli mode; anything too long for `Extension`
+/// comes after `@long`.
 macro_rules! ext {
-	($($v:ident $ext:literal),+ $(,)?) => (
+	(
+		$($v:ident $ext:literal $mode:ident),+ ,
+		@long
+		$($v2:ident $ext2:literal $mode2:ident),* $(,)?
+	) => (
 		$(
 			#[doc = concat!("# Extension (`", $ext, "`).")]
 			const $v: Extension = Extension::new($ext).unwrap();
 		)+
 
+		$(
+			#[doc = concat!("# Extension (`", $ext2, "`).")]
+			const $v2: &str = $ext2;
+		)*
+
 		/// # Match Extension.
 		///
 		/// This checks that the path (as a byte slice) ends with one of the
@@ -22,26 +40,22 @@ macro_rules! ext {
 			if let Some(ext) = Extension::from_path_slice(ext) {
 				matches!(ext, $($v) |+)
 			}
+			else { false $( || match_long(ext, $v2) )* }
+		}
+
+		/// # Brotli Mode.
+		///
+		/// Return the Brotli mode best suited to the path's (built-in) type,
+		/// or `BrotliMode::Generic` if it isn't one.
+		pub(super) const fn brotli_mode(ext: &[u8]) -> BrotliMode {
+			if let Some(ext) = Extension::from_path_slice(ext) {
+				$( if matches!(ext, $v) { return BrotliMode::$mode; } )+
+			}
 			else {
-				13 <= ext.len() &&
-				matches!(
-					ext,
-					[
-						.., 0..=46 | 48..=91 | 93..=255, b'.',
-						b'W' | b'w',
-						b'E' | b'e',
-						b'B' | b'b',
-						b'M' | b'm',
-						b'A' | b'a',
-						b'N' | b'n',
-						b'I' | b'i',
-						b'F' | b'f',
-						b'E' | b'e',
-						b'S' | b's',
-						b'T' | b't',
-					]
-				)
+				$( if match_long(ext, $v2) { return BrotliMode::$mode2; } )*
 			}
+
+			BrotliMode::Generic
 		}
 	);
 }
@@ -52,48 +66,145 @@ const EXT_BR: Extension = Extension::new("br").unwrap();
 /// # Extension (`gz`).
 const EXT_GZ: Extension = Extension::new("gz").unwrap();
 
-ext!{
-	EXT_JS       "js",
-	EXT_MD       "md",
-	EXT_BMP      "bmp",
-	EXT_CSS      "css",
-	EXT_EOT      "eot",
-	EXT_HTC      "htc",
-	EXT_HTM      "htm",
-	EXT_ICO      "ico",
-	EXT_ICS      "ics",
-	EXT_MJS      "mjs",
-	EXT_OTF      "otf",
-	EXT_RDF      "rdf",
-	EXT_RSS      "rss",
-	EXT_SVG      "svg",
-	EXT_TTF      "ttf",
-	EXT_TXT      "txt",
-	EXT_VCS      "vcs",
-	EXT_VTT      "vtt",
-	EXT_XML      "xml",
-	EXT_XSL      "xsl",
-	EXT_ATOM     "atom",
-	EXT_HTML     "html",
-	EXT_JSON     "json",
-	EXT_WASM     "wasm",
-	EXT_XHTM     "xhtm",
-	EXT_VCARD    "vcard",
-	EXT_XHTML    "xhtml",
-	EXT_JSONLD   "jsonld",
-	EXT_GEOJSON  "geojson",
-	EXT_APPCACHE "appcache",
-	EXT_MANIFEST "manifest",
+/// # Extension (`zst`).
+const EXT_ZST: Extension = Extension::new("zst").unwrap();
+
+/// # Extension (`dcb`).
+const EXT_DCB: Extension = Extension::new("dcb").unwrap();
+
+/// # Extension (`dcz`).
+const EXT_DCZ: Extension = Extension::new("dcz").unwrap();
+
+// Generated by build.rs.
+include!(concat!(env!("OUT_DIR"), "/ext.rs"));
+
+
+
+/// # File Types.
+///
+/// This holds any user-specified additions to and/or subtractions from the
+/// built-in extension list.
+#[derive(Debug, Clone, Default)]
+pub(super) struct FileTypes {
+	/// # Extra Extensions.
+	extra: Vec<String>,
+
+	/// # Skipped Extensions.
+	skip: Vec<String>,
 }
 
+impl FileTypes {
+	/// # Add Extension(s).
+	///
+	/// Add one or more comma-separated extensions to the list of types to
+	/// encode.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if any of the extensions are invalid.
+	pub(super) fn push_extra(&mut self, src: &str) -> Result<(), ChannelZError> {
+		Self::push(&mut self.extra, src)
+	}
+
+	/// # Skip Extension(s).
+	///
+	/// Add one or more comma-separated extensions to the list of types to
+	/// ignore, even if they'd otherwise be encoded.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if any of the extensions are invalid.
+	pub(super) fn push_skip(&mut self, src: &str) -> Result<(), ChannelZError> {
+		Self::push(&mut self.skip, src)
+	}
 
+	/// # Match Extension.
+	///
+	/// Same as [`match_extension`], but with the user's additions and
+	/// subtractions taken into account.
+	pub(super) fn matches(&self, path: &[u8]) -> bool {
+		if self.skip.iter().any(|e| match_long(path, e)) { false }
+		else {
+			match_extension(path) ||
+			self.extra.iter().any(|e| match_long(path, e))
+		}
+	}
 
-/// # Match br/gz.
+	/// # Push Extension(s).
+	///
+	/// Split, validate, normalize, and add the extension(s) to `list`.
+	fn push(list: &mut Vec<String>, src: &str) -> Result<(), ChannelZError> {
+		for ext in src.split(',') {
+			let ext = ext.trim().trim_start_matches('.').to_ascii_lowercase();
+			if
+				ext.is_empty() ||
+				! ext.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_')) ||
+				match_encoded(format!("x.{ext}").as_bytes())
+			{
+				return Err(ChannelZError::InvalidExtension(ext));
+			}
+
+			if ! list.contains(&ext) { list.push(ext); }
+		}
+
+		Ok(())
+	}
+}
+
+
+
+/// # Match Long Extension.
+///
+/// This checks that the path (as a byte slice) ends with a dot and `ext`,
+/// ignoring case. It is used for extensions too long to fit in an
+/// `Extension`, and for user-specified types.
+///
+/// Note: `ext` must be lowercase.
+const fn match_long(path: &[u8], ext: &str) -> bool {
+	let ext = ext.as_bytes();
+	if path.len() < ext.len() + 2 { return false; }
+	let (rest, tail) = path.split_at(path.len() - ext.len());
+	if ! matches!(rest, [.., 0..=46 | 48..=91 | 93..=255, b'.']) { return false; }
+
+	let mut idx = 0;
+	while idx < ext.len() {
+		if tail[idx].to_ascii_lowercase() != ext[idx] { return false; }
+		idx += 1;
+	}
+
+	true
+}
+
+/// # Match br/gz/zst/dcb/dcz.
 pub(super) const fn match_encoded(bytes: &[u8]) -> bool {
-	if let [.., 0..=46 | 48..=91 | 93..=255, b'.', a, b] = bytes {
-		matches!(Extension::new_slice(&[*a, *b]), Some(EXT_BR | EXT_GZ))
+	match bytes {
+		[.., 0..=46 | 48..=91 | 93..=255, b'.', a, b] =>
+			matches!(Extension::new_slice(&[*a, *b]), Some(EXT_BR | EXT_GZ)),
+		[.., 0..=46 | 48..=91 | 93..=255, b'.', a, b, c] =>
+			matches!(Extension::new_slice(&[*a, *b, *c]), Some(EXT_ZST | EXT_DCB | EXT_DCZ)),
+		_ => false,
+	}
+}
+
+/// # Encoded Kind.
+///
+/// Same as [`match_encoded`], but return the matching format instead of a
+/// simple yes or no.
+pub(super) const fn encoded_kind(bytes: &[u8]) -> Option<Flags> {
+	match bytes {
+		[.., 0..=46 | 48..=91 | 93..=255, b'.', a, b] => match Extension::new_slice(&[*a, *b]) {
+			Some(EXT_BR) => Some(Flags::Brotli),
+			Some(EXT_GZ) => Some(Flags::Gzip),
+			_ => None,
+		},
+		[.., 0..=46 | 48..=91 | 93..=255, b'.', a, b, c] => match Extension::new_slice(&[*a, *b, *c]) {
+			Some(EXT_ZST) => Some(Flags::Zstd),
+			Some(EXT_DCB) => Some(Flags::Dcb),
+			Some(EXT_DCZ) => Some(Flags::Dcz),
+			_ => None,
+		},
+		_ => None,
 	}
-	else { false }
 }
 
 
@@ -101,15 +212,17 @@ pub(super) const fn match_encoded(bytes: &[u8]) -> bool {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use std::collections::BTreeSet;
 
 	const BASE: &[u8] = b"/foo/bar/";
 	const FILE: &[u8] = b"file.";
-	const EXTS: [&[u8]; 32] = [
+	const EXTS: [&[u8]; 40] = [
 		b"js", b"md",
-		b"bmp", b"css", b"eot", b"htc", b"htm", b"ico", b"ics", b"mjs",
-		b"otf", b"rdf", b"rss", b"svg", b"ttf", b"txt", b"vcs", b"vtt",
-		b"xml", b"xsl",
-		b"atom", b"html", b"json", b"wasm", b"xhtm",
+		b"bmp", b"css", b"csv", b"doc", b"eot", b"htc", b"htm", b"ico",
+		b"ics", b"mjs", b"otf", b"pdf", b"rdf", b"rss", b"svg", b"ttf",
+		b"txt", b"vcs", b"vtt", b"xls", b"xml", b"xsl", b"yml",
+		b"atom", b"docx", b"html", b"json", b"wasm", b"xhtm", b"xlsx",
+		b"yaml",
 		b"vcard", b"xhtml",
 		b"jsonld",
 		b"geojson",
@@ -117,6 +230,35 @@ mod tests {
 		b"webmanifest",
 	];
 
+	/// # Help Screen File Types.
+	const HELP: &str = include_str!(concat!(env!("OUT_DIR"), "/help-file-types.txt"));
+
+	/// # Expand File Types.
+	///
+	/// Split a list of file types like "htm(l); js; y(a)ml" into individual
+	/// extensions.
+	fn expand(src: &str) -> BTreeSet<String> {
+		let mut out = BTreeSet::new();
+		for v in src.split([';', ' ', '\n']).filter(|v| ! v.is_empty()) {
+			if let Some((a, rest)) = v.split_once('(') {
+				let (b, c) = rest.split_once(')').expect("Unclosed file type.");
+				out.insert(format!("{a}{c}"));
+				out.insert(format!("{a}{b}{c}"));
+			}
+			else { out.insert(v.to_owned()); }
+		}
+		out
+	}
+
+	/// # Slice Between.
+	///
+	/// Return the part of `src` between `a` and `b`.
+	fn between<'a>(src: &'a str, a: &str, b: &str) -> &'a str {
+		let (_, rest) = src.split_once(a).expect("Missing start marker.");
+		let (out, _) = rest.split_once(b).expect("Missing end marker.");
+		out
+	}
+
 	#[test]
 	/// # Test Extension Finding.
 	fn t_ext() {
@@ -144,6 +286,104 @@ mod tests {
 		assert!(! match_extension(b""));
 	}
 
+	#[test]
+	/// # Test Advertised Extensions.
+	///
+	/// Make sure everything the help screen promises actually matches, and
+	/// that the packaging metadata and README promise the same things.
+	fn t_advertised() {
+		let help = expand(HELP);
+		assert_eq!(help.len(), EXTS.len(), "Help/test extension count mismatch.");
+		for ext in &help {
+			let path = [BASE, FILE, ext.as_bytes()].concat();
+			assert!(match_extension(&path), "Advertised extension not matched: {ext}");
+		}
+		for ext in EXTS {
+			let ext = std::str::from_utf8(ext).unwrap();
+			assert!(help.contains(ext), "Matched extension not advertised: {ext}");
+		}
+
+		// Debian package description.
+		let manifest = include_str!("../Cargo.toml");
+		let deb = between(
+			manifest,
+			"will be looked at:\\n\\\n",
+			"\"\"\"",
+		).replace("\\n\\", "");
+		assert_eq!(expand(&deb), help, "Cargo.toml (deb) file types are out of sync.");
+
+		// Bashman/man page.
+		let man = between(
+			manifest,
+			"generated for files with these extensions:\",\n",
+			"]",
+		).replace(['"', ',', '\t'], "");
+		assert_eq!(expand(&man), help, "Cargo.toml (bashman) file types are out of sync.");
+
+		// README.
+		let readme = between(
+			include_str!("../README.md"),
+			"file types are:\n",
+			"## Installation",
+		).replace(" * ", "");
+		assert_eq!(expand(&readme), help, "README file types are out of sync.");
+	}
+
+	#[test]
+	/// # Test Brotli Modes.
+	fn t_brotli_mode() {
+		for (path, mode) in [
+			("/font.ttf", BrotliMode::Font),
+			("/FONT.OTF", BrotliMode::Font),
+			("/font.eot", BrotliMode::Font),
+			("/index.html", BrotliMode::Text),
+			("/app.min.js", BrotliMode::Text),
+			("/data.json", BrotliMode::Text),
+			("/image.svg", BrotliMode::Text),
+			("/site.webmanifest", BrotliMode::Text),
+			("/feed.xml", BrotliMode::Text),
+			("/favicon.ico", BrotliMode::Generic),
+			("/doc.pdf", BrotliMode::Generic),
+			("/unknown.bin", BrotliMode::Generic),
+			("/ttf", BrotliMode::Generic),
+		] {
+			assert_eq!(brotli_mode(path.as_bytes()), mode, "Wrong brotli mode for {path}.");
+		}
+	}
+
+	#[test]
+	/// # Test User File Types.
+	fn t_file_types() {
+		let mut types = FileTypes::default();
+		assert!(types.matches(b"/foo/file.js"));
+		assert!(types.matches(b"/foo/file.ico"));
+		assert!(! types.matches(b"/foo/file.map"));
+
+		types.push_extra("map, .WAT,gltf").expect("Extra types failed.");
+		types.push_skip("bmp,ico").expect("Skip types failed.");
+		assert!(types.matches(b"/foo/file.js"));
+		assert!(types.matches(b"/foo/file.js.map"));
+		assert!(types.matches(b"/foo/file.MAP"));
+		assert!(types.matches(b"/foo/file.wat"));
+		assert!(types.matches(b"/foo/file.gltf"));
+		assert!(! types.matches(b"/foo/file.ico"));
+		assert!(! types.matches(b"/foo/file.BMP"));
+		assert!(! types.matches(b"/foo/.map"));
+		assert!(! types.matches(b"/foo/filemap"));
+
+		// Skips win.
+		types.push_skip("map").expect("Skip types failed.");
+		assert!(! types.matches(b"/foo/file.map"));
+
+		// Bad extensions.
+		assert!(types.push_extra("").is_err());
+		assert!(types.push_extra("js,").is_err());
+		assert!(types.push_extra("j s").is_err());
+		assert!(types.push_extra("gz").is_err());
+		assert!(types.push_skip("BR").is_err());
+		assert!(types.push_skip("zst").is_err());
+	}
+
 	#[test]
 	/// # Test Extension Finding.
 	fn t_br_gz() {
@@ -168,8 +408,44 @@ mod tests {
 
 			let bad = [BASE, b".br"].concat();
 			assert!(! match_encoded(&bad));
+
+			let mut fine = [BASE, FILE, ext, b".zst"].concat();
+			assert!(match_encoded(&fine));
+
+			fine.make_ascii_uppercase();
+			assert!(match_encoded(&fine));
+
+			let bad = [BASE, b".zst"].concat();
+			assert!(! match_encoded(&bad));
+
+			for enc in [&b".dcb"[..], b".DCZ"] {
+				let fine = [BASE, FILE, ext, enc].concat();
+				assert!(match_encoded(&fine));
+
+				let bad = [BASE, enc].concat();
+				assert!(! match_encoded(&bad));
+			}
 		}
 
 		assert!(! match_encoded(b"/foo/foo.js"));
+		assert!(! match_encoded(b"/foo/foo.zs"));
+		assert!(! match_encoded(b"/foo/foo.zstd"));
+	}
+
+	#[test]
+	fn t_encoded_kind() {
+		for (path, expected) in [
+			(&b"/foo/file.js.br"[..], Some(Flags::Brotli)),
+			(b"/foo/file.js.GZ", Some(Flags::Gzip)),
+			(b"/foo/file.js.zst", Some(Flags::Zstd)),
+			(b"/foo/file.js.dcb", Some(Flags::Dcb)),
+			(b"/foo/file.js.Dcz", Some(Flags::Dcz)),
+			(b"/foo/file.js", None),
+			(b"/foo/.br", None),
+			(b"/foo/file.zstd", None),
+		] {
+			assert_eq!(encoded_kind(path), expected, "Wrong kind for {path:?}.");
+			assert_eq!(encoded_kind(path).is_some(), match_encoded(path), "Mismatch for {path:?}.");
+		}
 	}
 }
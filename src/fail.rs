@@ -0,0 +1,96 @@
+/*!
+# ChannelZ: Failures
+
+This module holds the per-file errors encountered while crunching.
+*/
+
+use crate::{
+	LogKind,
+	PathLog,
+	report::json_str,
+};
+
+
+
+#[derive(Debug, Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+/// # Failure Kind.
+///
+/// The ways a single file can go wrong.
+pub(super) enum FailKind {
+	/// # Unable to Read Source.
+	Read,
+
+	/// # Unable to Encode.
+	Encode,
+
+	/// # Encoded Copy Failed Verification.
+	Verify,
+
+	/// # Unable to Write Encoded Copy.
+	Write,
+
+	/// # Unable to Remove Stale Encoded Copy.
+	Remove,
+
+	/// # Unable to Decode Encoded Copy.
+	Decode,
+}
+
+impl LogKind for FailKind {
+	/// # As Label.
+	fn as_str(self) -> &'static str {
+		match self {
+			Self::Read =>   "    Read",
+			Self::Encode => "  Encode",
+			Self::Verify => "  Verify",
+			Self::Write =>  "   Write",
+			Self::Remove => "  Remove",
+			Self::Decode => "  Decode",
+		}
+	}
+}
+
+impl FailKind {
+	/// # As Name.
+	///
+	/// Return the (lowercase) name used in reports.
+	const fn name(self) -> &'static str {
+		match self {
+			Self::Read => "read",
+			Self::Encode => "encode",
+			Self::Verify => "verify",
+			Self::Write => "write",
+			Self::Remove => "remove",
+			Self::Decode => "decode",
+		}
+	}
+}
+
+
+
+/// # Failures.
+///
+/// This holds the paths — sources for read errors, encoded copies for
+/// everything else — that ran into trouble, along with the kind of trouble.
+///
+/// When decoding, read and decode errors are noted against the encoded copies,
+/// and write errors against the restored originals.
+pub(super) type Failures = PathLog<FailKind>;
+
+impl Failures {
+	/// # Write JSON.
+	///
+	/// Append the failures to `out` as a JSON array of objects.
+	pub(super) fn write_json(&self, out: &mut String) {
+		out.push('[');
+		for (k, (path, kind)) in self.iter().enumerate() {
+			if k != 0 { out.push(','); }
+			out.push_str("{\"path\":");
+			json_str(&path.to_string_lossy(), out);
+			out.push_str(",\"kind\":\"");
+			out.push_str(kind.name());
+			out.push_str("\"}");
+		}
+		out.push(']');
+	}
+}
@@ -57,9 +57,23 @@
 
 
 mod abacus;
+mod check;
+mod config;
+mod dec;
+mod delta;
+mod dict;
 mod enc;
 mod err;
+mod exclude;
 mod ext;
+mod fail;
+mod mirror;
+mod pathlog;
+mod plan;
+mod report;
+mod restore;
+mod settings;
+mod train;
 
 
 
@@ -67,25 +81,72 @@ use abacus::{
 	EncoderTotals,
 	ThreadTotals,
 };
+use check::{
+	Problem,
+	Problems,
+};
 use flume::Receiver;
 use dactyl::NiceU64;
+use delta::{
+	Delta,
+	Pattern,
+};
+use dict::Dictionary;
 use dowser::Dowser;
+use enc::{
+	BR_QUALITY,
+	BrotliMode,
+	BR_WINDOW,
+	EncoderOptions,
+	GZ_LEVEL,
+	GzipEngine,
+	MinSavings,
+};
 use err::ChannelZError;
+use exclude::Exclude;
+use ext::FileTypes;
+use fail::{
+	FailKind,
+	Failures,
+};
 use fyi_msg::{
 	fyi_ansi::dim,
 	Msg,
 	MsgKind,
 	Progless,
 };
+use mirror::OutDir;
+use pathlog::{
+	LogKind,
+	PathLog,
+};
+use plan::{
+	Action,
+	Plan,
+};
+use report::{
+	Report,
+	ReportTo,
+};
+use restore::Restorer;
+use settings::{
+	Settings,
+	TrainSettings,
+};
 use std::{
+	ffi::OsStr,
 	num::NonZeroUsize,
+	ops::Add,
 	os::unix::ffi::OsStrExt,
 	path::{
 		Path,
 		PathBuf,
 	},
 	process::ExitCode,
-	sync::atomic::Ordering::SeqCst,
+	sync::atomic::{
+		AtomicBool,
+		Ordering::SeqCst,
+	},
 	thread,
 };
 
@@ -100,6 +161,15 @@ const EXT_BR: u16 = u16::from_le_bytes(*b"br");
 /// # Extension: Gzip.
 const EXT_GZ: u16 = u16::from_le_bytes(*b"gz");
 
+/// # Extension: Zstd.
+const EXT_ZST: u32 = u32::from_le_bytes(*b"zst\0");
+
+/// # Extension: Dictionary-Compressed Brotli.
+const EXT_DCB: u32 = u32::from_le_bytes(*b"dcb\0");
+
+/// # Extension: Dictionary-Compressed Zstd.
+const EXT_DCZ: u32 = u32::from_le_bytes(*b"dcz\0");
+
 
 
 /// # Main.
@@ -120,74 +190,63 @@ fn main() -> ExitCode {
 #[inline]
 /// # Actual Main.
 fn main__() -> Result<(), ChannelZError> {
-	argyle::argue! {
-		Clean         "--clean",
-		CleanOnly     "--clean-only",
-		Force         "--force",
-		NoBr          "--no-br",
-		NoGz          "--no-gz",
-		Progress "-p" "--progress",
-		Help     "-h" "--help",
-		Version  "-V" "--version",
-
-		@options
-		List     "-l" "--list",
-
-		@catchall-paths Path,
+	// Training a dictionary instead?
+	if std::env::args_os().nth(1).is_some_and(|a| a == "train-dict") {
+		return train::train(TrainSettings::from_cli()?);
 	}
 
-	// Parse CLI arguments.
-	let mut kinds = Flags::All;
-	let mut paths = Dowser::default();
-	let mut progress = false;
-	for arg in Argument::args_os() {
-		match arg {
-			Argument::Clean => { kinds.set(Flags::Clean); },
-			Argument::CleanOnly => { kinds.set(Flags::CleanOnly); },
-			Argument::Force => { kinds.set(Flags::Force); },
-			Argument::NoBr => { kinds.unset(Flags::Brotli); },
-			Argument::NoGz => { kinds.unset(Flags::Gzip); },
-			Argument::Progress => { progress = true; },
-
-			Argument::Help => return Err(ChannelZError::PrintHelp),
-			Argument::Version => return Err(ChannelZError::PrintVersion),
-
-			Argument::List(s) =>
-				if s == "-" { paths.push_paths_from_stdin(); }
-				else {
-					paths.push_paths_from_file(s).map_err(|_| ChannelZError::ListFile)?;
-				},
-
-			// Assume paths.
-			Argument::Path(s) => { paths = paths.with_path(s); },
-
-			// Mistakes?
-			Argument::Other(s) =>   return Err(ChannelZError::InvalidCli(s)),
-			Argument::OtherOs(s) => return Err(ChannelZError::InvalidCli(s.to_string_lossy().into_owned())),
-		}
+	let Settings {
+		kinds, paths, progress, types, mut exclude, opts, check, prune, decode, strict, report,
+		out_dir, dict, delta, stdout,
+	} = Settings::from_cli()?;
+
+	// Just streaming?
+	if stdout { return stream(kinds, opts); }
+
+	let out_dir = out_dir.as_ref();
+	let dict = dict.as_ref();
+	let delta = delta.as_ref();
+
+	// Decoding instead?
+	if let Some(max) = decode {
+		return decode_all(paths, progress, kinds, &types, &mut exclude, dict, max.get(), opts.preserve);
 	}
 
-	// Nothing?
-	kinds.contains_any(Flags::All).ok_or(ChannelZError::NoEncoders)?;
+	// Just checking?
+	if check {
+		return check_all(paths, progress, kinds, &types, &mut exclude, opts, out_dir, dict, delta);
+	}
 
-	// Clean first?
+	// In dry-run mode, the changes are collected instead of made.
+	let mut plan = opts.dry_run.then(Plan::new);
+
+	// If the report is going to STDOUT, everything else goes to STDERR.
+	let stderr = matches!(report, Some(ReportTo::Stdout));
+
+	// Clean first? (When mirroring, it's the mirror that gets cleaned.)
 	if kinds.contains(Flags::Clean) {
-		clean(paths.clone(), progress, kinds);
-		if kinds.contains(Flags::CleanOnly) { return Ok(()); }
+		let (crawl, mut exclude) = mirror_crawl(&paths, &exclude, out_dir);
+		clean(crawl, progress, stderr, kinds, &types, &mut exclude, plan.as_mut());
+		if kinds.contains(Flags::CleanOnly) {
+			if let Some(mut plan) = plan { plan.summarize(stderr); }
+			return Ok(());
+		}
+	}
+	// Or just prune the orphans? (Ditto.)
+	else if prune {
+		let (crawl, mut exclude) = mirror_crawl(&paths, &exclude, out_dir);
+		self::prune(crawl, progress, stderr, kinds, &types, &mut exclude, out_dir, plan.as_mut());
 	}
 
 	// Put it all together!
 	let mut paths: Vec<PathBuf> =
-		if kinds.contains(Flags::Force) { paths.filter(find_all).collect() }
-		else { paths.filter(find_default).collect() };
+		if kinds.contains(Flags::Force) {
+			paths.filter(|p| find_all(p) && ! exclude.excludes(p)).collect()
+		}
+		else { paths.filter(|p| find_default(p, &types) && ! exclude.excludes(p)).collect() };
 	let total = NonZeroUsize::new(paths.len()).ok_or(ChannelZError::NoFiles)?;
 	paths.sort();
-
-	// How many threads?
-	let threads = thread::available_parallelism().map_or(
-		NonZeroUsize::MIN,
-		|t| NonZeroUsize::min(t, total),
-	);
+	if let Some(o) = out_dir { o.check_sources(&paths)?; }
 
 	// Set up the killswitch.
 	let killed = Progless::sigint_two_strike();
@@ -202,60 +261,156 @@ fn main__() -> Result<(), ChannelZError> {
 		else { None };
 
 	// Thread business!
-	let (tx, rx) = flume::bounded::<&Path>(threads.get());
-	let len = thread::scope(#[inline(always)] |s| {
-		// Set up the worker threads.
-		let mut workers = Vec::with_capacity(threads.get());
-		for _ in 0..threads.get() {
-			let rx2 = rx.clone();
-			workers.push(s.spawn(#[inline(always)] || crunch(rx2, kinds, progress.as_ref())));
+	let (len, mut errs, mut files, plan2) = jobserver(
+		&paths,
+		killed,
+		(ThreadTotals::new(), Failures::new(), Report::new(), Plan::new()),
+		|(a, b, c, d), (e, f, g, h)| (a + e, b + f, c + g, d + h),
+		#[inline(always)] |rx| crunch(rx, kinds, opts, out_dir, dict, delta, progress.as_ref(), report.is_some()),
+	)?;
+
+	// Summarize? (Dry runs always do.)
+	let summarize = progress.is_some() || plan.is_some();
+	if let Some(progress) = progress {
+		progress.finish();
+		print_msg(&progress.summary(MsgKind::Crunched, "file", "files"), stderr);
+	}
+	if let Some(plan) = plan { (plan + plan2).summarize(stderr); }
+	if summarize { len.summarize(kinds, stderr); }
+
+	// Early abort?
+	if killed.load(SeqCst) { return Err(ChannelZError::Killed); }
+
+	// Mention any failures.
+	if ! errs.is_empty() { errs.summarize(true); }
+
+	// Save the report?
+	if let Some(dst) = report { files.save(&dst, &len, &errs, kinds, opts.dry_run)?; }
+
+	if errs.is_empty() { Ok(()) }
+	else if strict { Err(ChannelZError::Strict) }
+	else if errs.contains(FailKind::Verify) { Err(ChannelZError::Verify) }
+	else { Ok(()) }
+}
+
+#[expect(clippy::too_many_arguments, reason = "It is what it is.")]
+/// # Check.
+///
+/// Verify the (enabled) encoded copies for each source are present and
+/// decode back to the original, and that there aren't any encoded copies
+/// lying around for sources that no longer exist (or are empty).
+///
+/// Nothing is written or removed. Problems are printed, and returned as an
+/// error so the process exits non-zero.
+///
+/// When mirroring to an output directory, the encoded copies are looked for
+/// there instead.
+fn check_all(
+	paths: Dowser,
+	progress: bool,
+	kinds: Flags,
+	types: &FileTypes,
+	exclude: &mut Exclude,
+	opts: EncoderOptions,
+	out_dir: Option<&OutDir>,
+	dict: Option<&Dictionary>,
+	delta: Option<&Delta>,
+) -> Result<(), ChannelZError> {
+	let force = kinds.contains(Flags::Force);
+
+	// Split the crawl into sources and orphans.
+	let mut sources = Vec::new();
+	let mut problems = Problems::new();
+	for p in paths {
+		if exclude.excludes(&p) { continue; }
+		if strip_encoded(p.as_os_str().as_bytes(), kinds).is_some() {
+			if out_dir.is_none() && orphaned(&p, kinds, types, None) {
+				problems.push(p, Problem::Orphan);
+			}
 		}
-		drop(rx);
+		else if if force { find_all(&p) } else { find_default(&p, types) } {
+			sources.push(p);
+		}
+	}
 
-		// Push all the files to it, then drop the sender to disconnect.
-		for path in &paths {
-			if killed.load(SeqCst) || tx.send(path).is_err() { break; }
+	// Orphans in the mirror have to be mapped back to their sources.
+	if let Some(out) = out_dir {
+		let mut exclude = exclude.mirror(out.dir());
+		for p in Dowser::from(out.dir()) {
+			if orphaned(&p, kinds, types, out_dir) && ! exclude.excludes(&p) {
+				problems.push(p, Problem::Orphan);
+			}
 		}
-		drop(tx);
+	}
+	if sources.is_empty() && problems.is_empty() { return Err(ChannelZError::NoFiles); }
+	sources.sort();
+	if let Some(o) = out_dir { o.check_sources(&sources)?; }
 
-		// Sum the totals as each thread finishes.
-		// TODO: prefer try_reduce() when stable.
-		workers.into_iter()
-			.try_fold(ThreadTotals::new(), |acc, worker|
-				worker.join().map(|len2| acc + len2)
-			)
-			.map_err(|_| ChannelZError::Jobserver)
-	})?;
+	// Set up the killswitch.
+	let killed = Progless::sigint_two_strike();
 
-	// Summarize?
-	if let Some(progress) = progress {
-		progress.finish();
-		progress.summary(MsgKind::Crunched, "file", "files").print();
-		len.summarize(kinds);
+	// Boot up a progress bar, if desired.
+	let progress =
+		if progress {
+			NonZeroUsize::new(sources.len())
+				.and_then(|total| Progless::try_from(total).ok())
+				.map(|p| p.with_reticulating_splines("ChannelZ"))
+		}
+		else { None };
+
+	// Check the sources.
+	if ! sources.is_empty() {
+		problems = problems + jobserver(
+			&sources,
+			killed,
+			Problems::new(),
+			Add::add,
+			#[inline(always)] |rx| inspect(rx, kinds, opts, out_dir, dict, delta, progress.as_ref()),
+		)?;
 	}
 
-	// Early abort?
-	if killed.load(SeqCst) { Err(ChannelZError::Killed) }
-	else { Ok(()) }
+	if let Some(progress) = progress { progress.finish(); }
+	if killed.load(SeqCst) { return Err(ChannelZError::Killed); }
+
+	// Report the results.
+	if problems.is_empty() {
+		Msg::success("All encoded copies are present and correct.").print();
+		Ok(())
+	}
+	else {
+		problems.summarize(true);
+		Err(ChannelZError::Check)
+	}
 }
 
 /// # Clean.
 ///
 /// This will run a separate search over the specified paths with the sole
-/// purpose of removing `*.gz` and/or `*.br` files.
-fn clean(paths: Dowser, summary: bool, kinds: Flags) {
+/// purpose of removing `*.gz`, `*.br`, and/or `*.zst` files (as well as
+/// their `*.dcb`/`*.dcz` dictionary-compressed counterparts).
+///
+/// Copies of excluded sources are left alone.
+///
+/// In dry-run mode, the deletions are added to the plan instead.
+fn clean(
+	paths: Dowser,
+	summary: bool,
+	stderr: bool,
+	kinds: Flags,
+	types: &FileTypes,
+	exclude: &mut Exclude,
+	mut plan: Option<&mut Plan>,
+) {
 	let has_br = kinds.contains(Flags::Brotli);
 	let has_gz = kinds.contains(Flags::Gzip);
+	let has_zst = kinds.contains(Flags::Zstd);
 
 	let mut cleaned = 0_u64;
 	for p in paths {
-		let [rest @ .., b'.', y, z] = p.as_os_str().as_bytes() else { continue; };
-		let ext = u16::from_le_bytes([y.to_ascii_lowercase(), z.to_ascii_lowercase()]);
-		if
-			((has_br && ext == EXT_BR) || (has_gz && ext == EXT_GZ)) &&
-			ext::match_extension(rest)
-		{
-			if std::fs::remove_file(&p).is_ok() { cleaned += 1; }
+		let Some(rest) = strip_encoded(p.as_os_str().as_bytes(), kinds) else { continue; };
+		if types.matches(rest) && ! exclude.excludes(&p) {
+			if let Some(plan) = &mut plan { plan.push(p, Action::Delete); }
+			else if std::fs::remove_file(&p).is_ok() { cleaned += 1; }
 			else {
 				Msg::warning(format!(
 					concat!("Unable to delete ", dim!("{p}")),
@@ -265,45 +420,399 @@ fn clean(paths: Dowser, summary: bool, kinds: Flags) {
 		}
 	}
 
-	if summary {
-		if cleaned == 0 { Msg::info("There was nothing to clean.") }
+	if summary && plan.is_none() {
+		let msg =
+			if cleaned == 0 { Msg::info("There was nothing to clean.") }
+			else {
+				let encoded = [
+					(has_br, "br"), (has_gz, "gz"), (has_zst, "zst"),
+					(has_br, "dcb"), (has_zst, "dcz"),
+				]
+					.into_iter()
+					.filter_map(|(enabled, label)| enabled.then_some(label))
+					.collect::<Vec<&str>>()
+					.join("/");
+
+				Msg::success(format!(
+					"Removed {} old {encoded}-encoded {}.",
+					NiceU64::from(cleaned),
+					if cleaned == 1 { "copy" } else { "copies" },
+				))
+			};
+		print_msg(&msg, stderr);
+	}
+}
+
+#[expect(clippy::too_many_arguments, reason = "It is what it is.")]
+/// # Prune.
+///
+/// This will run a separate search over the specified paths with the sole
+/// purpose of removing the (enabled) encoded copies whose sources no longer
+/// exist, or are empty.
+///
+/// When mirroring, the paths are assumed to be in the output directory.
+///
+/// In dry-run mode, the deletions are added to the plan instead.
+fn prune(
+	paths: Dowser,
+	summary: bool,
+	stderr: bool,
+	kinds: Flags,
+	types: &FileTypes,
+	exclude: &mut Exclude,
+	out_dir: Option<&OutDir>,
+	mut plan: Option<&mut Plan>,
+) {
+	let mut pruned = 0_u64;
+	for p in paths {
+		if orphaned(&p, kinds, types, out_dir) && ! exclude.excludes(&p) {
+			if let Some(plan) = &mut plan { plan.push(p, Action::Delete); }
+			else if std::fs::remove_file(&p).is_ok() { pruned += 1; }
+			else {
+				Msg::warning(format!(
+					concat!("Unable to delete ", dim!("{p}")),
+					p=p.display(),
+				)).eprint();
+			}
+		}
+	}
+
+	if summary && plan.is_none() {
+		let msg =
+			if pruned == 0 { Msg::info("There was nothing to prune.") }
+			else {
+				Msg::success(format!(
+					"Removed {} orphaned encoded {}.",
+					NiceU64::from(pruned),
+					if pruned == 1 { "copy" } else { "copies" },
+				))
+			};
+		print_msg(&msg, stderr);
+	}
+}
+
+#[expect(clippy::too_many_arguments, reason = "It is what it is.")]
+/// # Decode.
+///
+/// Restore the originals from the (enabled) encoded copies found in the
+/// crawl — skipping any that already exist — so long as they decode to no
+/// more than `max` bytes.
+///
+/// If the same original has several encoded copies, only one of them is
+/// used.
+fn decode_all(
+	paths: Dowser,
+	progress: bool,
+	kinds: Flags,
+	types: &FileTypes,
+	exclude: &mut Exclude,
+	dict: Option<&Dictionary>,
+	max: usize,
+	preserve: bool,
+) -> Result<(), ChannelZError> {
+	let force = kinds.contains(Flags::Force);
+
+	// Find the encoded copies, one per original.
+	let mut paths: Vec<PathBuf> = paths.filter(|p| {
+		let bytes = p.as_os_str().as_bytes();
+		strip_encoded(bytes, kinds).is_some_and(|rest| force || types.matches(rest)) &&
+		ext::encoded_kind(bytes).is_some_and(|kind| restore::decodable(kind, dict.is_some())) &&
+		! exclude.excludes(p)
+	}).collect();
+	paths.sort();
+	paths.dedup_by(|a, b| a.with_extension("") == b.with_extension(""));
+	let total = NonZeroUsize::new(paths.len()).ok_or(ChannelZError::NoFiles)?;
+
+	// Set up the killswitch.
+	let killed = Progless::sigint_two_strike();
+
+	// Boot up a progress bar, if desired.
+	let progress =
+		if progress {
+			Progless::try_from(total)
+				.ok()
+				.map(|p| p.with_reticulating_splines("ChannelZ"))
+		}
+		else { None };
+
+	// Thread business!
+	let (restored, mut errs) = jobserver(
+		&paths,
+		killed,
+		(0_u64, Failures::new()),
+		|(a, b), (c, d)| (a + c, b + d),
+		#[inline(always)] |rx| unpack(rx, dict, max, preserve, progress.as_ref()),
+	)?;
+
+	// Summarize?
+	if let Some(progress) = progress {
+		progress.finish();
+		if restored == 0 { Msg::info("There was nothing to restore.") }
 		else {
 			Msg::success(format!(
-				"Removed {} old {}-encoded {}.",
-				NiceU64::from(cleaned),
-				if has_br && has_gz { "br/gz" }
-				else if has_br { "br" }
-				else { "gz" },
-				if cleaned == 1 { "copy" } else { "copies" },
+				"Restored {} original {}.",
+				NiceU64::from(restored),
+				if restored == 1 { "file" } else { "files" },
 			))
 		}
 		.print();
 	}
+
+	// Early abort?
+	if killed.load(SeqCst) { return Err(ChannelZError::Killed); }
+
+	if errs.is_empty() { Ok(()) }
+	else {
+		errs.summarize(true);
+		Err(ChannelZError::Decode)
+	}
+}
+
+/// # Stream.
+///
+/// Encode STDIN to STDOUT with the (one) enabled format.
+fn stream(kind: Flags, opts: EncoderOptions) -> Result<(), ChannelZError> {
+	use std::io::Write;
+
+	let mut enc = enc::Encoder::new(kind, opts);
+	let out = enc.encode_reader(std::io::stdin().lock(), kind).ok_or(ChannelZError::Stream)?;
+	let mut stdout = std::io::stdout().lock();
+	stdout.write_all(out)
+		.and_then(|()| stdout.flush())
+		.map_err(|_| ChannelZError::Stream)
 }
 
 #[expect(clippy::needless_pass_by_value, reason = "For drop.")]
+#[expect(clippy::too_many_arguments, reason = "It is what it is.")]
 #[inline(never)]
 /// # Worker Callback.
 ///
 /// This is the worker callback for crunching. It listens for "new" file paths
 /// and crunches them — and maybe updates the progress bar, etc. — then quits
 /// as soon as the work has dried up.
-fn crunch(rx: Receiver::<&Path>, kinds: Flags, progress: Option<&Progless>) -> ThreadTotals {
-	let mut enc = enc::Encoder::new(kinds);
+///
+/// If `report` is true, the per-file totals are collected too. In dry-run
+/// mode, so are the changes that would have been made.
+fn crunch(
+	rx: Receiver::<&Path>,
+	kinds: Flags,
+	opts: EncoderOptions,
+	out_dir: Option<&OutDir>,
+	dict: Option<&Dictionary>,
+	delta: Option<&Delta>,
+	progress: Option<&Progless>,
+	report: bool,
+) -> (ThreadTotals, Failures, Report, Plan) {
+	let mut enc = enc::Encoder::new(kinds, opts)
+		.with_out_dir(out_dir)
+		.with_dictionary(dict)
+		.with_delta(delta);
 	let mut len = ThreadTotals::new();
+	let mut errs = Failures::new();
+	let mut files = Report::new();
 
-	let Some(progress) = progress else {
-		while let Ok(p) = rx.recv() { let _res = enc.encode(p); }
-		return len;
-	};
+	while let Ok(p) = rx.recv() {
+		let task = progress.map(|progress| progress.task(p.to_string_lossy()));
+		if let Some(len2) = enc.encode(p, &mut errs) {
+			len += len2;
+			if report { files.push(p, len2); }
+		}
+		drop(task);
+	}
+
+	(len, errs, files, enc.take_plan())
+}
+
+#[expect(clippy::needless_pass_by_value, reason = "For drop.")]
+#[inline(never)]
+/// # Worker Callback (Check).
+///
+/// This is the worker callback for `--check`. It works just like `crunch`,
+/// except nothing gets written; problems are collected and returned instead.
+fn inspect(
+	rx: Receiver::<&Path>,
+	kinds: Flags,
+	opts: EncoderOptions,
+	out_dir: Option<&OutDir>,
+	dict: Option<&Dictionary>,
+	delta: Option<&Delta>,
+	progress: Option<&Progless>,
+) -> Problems {
+	let mut enc = enc::Encoder::new(kinds, opts)
+		.with_out_dir(out_dir)
+		.with_dictionary(dict)
+		.with_delta(delta);
+	let mut problems = Problems::new();
+
+	while let Ok(p) = rx.recv() {
+		let task = progress.map(|progress| progress.task(p.to_string_lossy()));
+		enc.check(p, &mut problems);
+		drop(task);
+	}
+
+	problems
+}
+
+#[expect(clippy::needless_pass_by_value, reason = "For drop.")]
+#[inline(never)]
+/// # Worker Callback (Decode).
+///
+/// This is the worker callback for `--decode`. It listens for encoded copies
+/// and restores their originals, returning the number restored and any
+/// failures.
+fn unpack(
+	rx: Receiver::<&Path>,
+	dict: Option<&Dictionary>,
+	max: usize,
+	preserve: bool,
+	progress: Option<&Progless>,
+) -> (u64, Failures) {
+	let mut dec = Restorer::new(dict, max, preserve);
+	let mut restored = 0;
+	let mut errs = Failures::new();
 
 	while let Ok(p) = rx.recv() {
-		let task = progress.task(p.to_string_lossy());
-		if let Some(len2) = enc.encode(p) { len += len2; }
+		let task = progress.map(|progress| progress.task(p.to_string_lossy()));
+		if dec.restore(p, &mut errs) { restored += 1; }
 		drop(task);
 	}
 
-	len
+	(restored, errs)
+}
+
+/// # Jobserver.
+///
+/// Spin up a worker thread for each available core (up to the number of
+/// paths), feed them the paths, and merge their results.
+///
+/// The callback is expected to process paths until the channel dries up.
+fn jobserver<T, M, F>(
+	paths: &[PathBuf],
+	killed: &AtomicBool,
+	init: T,
+	merge: M,
+	cb: F,
+) -> Result<T, ChannelZError>
+where
+	T: Send,
+	M: Fn(T, T) -> T,
+	F: Fn(Receiver::<&Path>) -> T + Sync,
+{
+	// How many threads?
+	let threads = thread::available_parallelism().map_or(
+		NonZeroUsize::MIN,
+		|t| NonZeroUsize::new(paths.len()).map_or(NonZeroUsize::MIN, |total| t.min(total)),
+	);
+
+	let (tx, rx) = flume::bounded::<&Path>(threads.get());
+	thread::scope(#[inline(always)] |s| {
+		// Set up the worker threads.
+		let cb = &cb;
+		let mut workers = Vec::with_capacity(threads.get());
+		for _ in 0..threads.get() {
+			let rx2 = rx.clone();
+			workers.push(s.spawn(#[inline(always)] move || cb(rx2)));
+		}
+		drop(rx);
+
+		// Push all the files to it, then drop the sender to disconnect.
+		for path in paths {
+			if killed.load(SeqCst) || tx.send(path).is_err() { break; }
+		}
+		drop(tx);
+
+		// Sum the totals as each thread finishes.
+		// TODO: prefer try_reduce() when stable.
+		workers.into_iter()
+			.try_fold(init, |acc, worker|
+				worker.join().map(|len2| merge(acc, len2))
+			)
+			.map_err(|_| ChannelZError::Jobserver)
+	})
+}
+
+/// # Strip Encoded Extension.
+///
+/// If the path ends with the extension of one of the enabled encodings —
+/// `.br`, `.gz`, `.zst` — return the (source) path without it.
+///
+/// The dictionary-compressed `.dcb` and `.dcz` extensions are lumped in with
+/// brotli and zstd respectively.
+fn strip_encoded(p: &[u8], kinds: Flags) -> Option<&[u8]> {
+	match p {
+		[rest @ .., b'.', y, z] => {
+			let ext = u16::from_le_bytes([y.to_ascii_lowercase(), z.to_ascii_lowercase()]);
+			if
+				(kinds.contains(Flags::Brotli) && ext == EXT_BR) ||
+				(kinds.contains(Flags::Gzip) && ext == EXT_GZ)
+			{
+				Some(rest)
+			}
+			else { None }
+		},
+		[rest @ .., b'.', x, y, z] => {
+			let ext = u32::from_le_bytes([
+				x.to_ascii_lowercase(),
+				y.to_ascii_lowercase(),
+				z.to_ascii_lowercase(),
+				0,
+			]);
+			if
+				(kinds.contains(Flags::Zstd) && (ext == EXT_ZST || ext == EXT_DCZ)) ||
+				(kinds.contains(Flags::Brotli) && ext == EXT_DCB)
+			{
+				Some(rest)
+			}
+			else { None }
+		},
+		_ => None,
+	}
+}
+
+/// # Mirror Crawl.
+///
+/// Return the crawl and exclusions for the clean/prune passes, which run
+/// against the output directory instead of the inputs when mirroring.
+fn mirror_crawl(paths: &Dowser, exclude: &Exclude, out_dir: Option<&OutDir>)
+-> (Dowser, Exclude) {
+	out_dir.map_or_else(
+		|| (paths.clone(), exclude.clone()),
+		|o| (Dowser::from(o.dir()), exclude.mirror(o.dir())),
+	)
+}
+
+/// # Print Message.
+///
+/// Print the message to STDOUT, or STDERR if `stderr`, e.g. because STDOUT
+/// is reserved for the JSON report.
+fn print_msg(msg: &Msg, stderr: bool) {
+	if stderr { msg.eprint(); }
+	else { msg.print(); }
+}
+
+/// # Orphaned?
+///
+/// Returns `true` if the path is an (enabled) encoded copy — of a type
+/// ChannelZ would encode — whose source no longer exists, or is empty.
+///
+/// When mirroring, the path is assumed to be in the output directory, and is
+/// only considered orphaned if none of its possible sources are around.
+fn orphaned(p: &Path, kinds: Flags, types: &FileTypes, out_dir: Option<&OutDir>) -> bool {
+	/// # Missing or Empty?
+	fn missing(src: &Path) -> bool {
+		std::fs::metadata(src).map_or(true, |m| ! m.is_file() || m.len() == 0)
+	}
+
+	// Even with --force, only copies of types we'd encode anyway are fair
+	// game; there's no telling what else might be lying around.
+	let Some(rest) = strip_encoded(p.as_os_str().as_bytes(), kinds) else { return false; };
+	if ! types.matches(rest) { return false; }
+
+	let src = Path::new(OsStr::from_bytes(rest));
+	out_dir.map_or_else(
+		|| missing(src),
+		|out| out.unmap(src).iter().all(|src| missing(src)),
+	)
 }
 
 #[cold]
@@ -314,15 +823,17 @@ fn crunch(rx: Receiver::<&Path>, kinds: Flags, progress: Option<&Progless>) -> T
 /// be valid, canonical _files_; all we need to do is check the extensions.
 ///
 /// For this variation, everything is fair game so long as it isn't already
-/// `gz`/`br`-encoded.
+/// `gz`/`br`/`zst`-encoded.
 fn find_all(p: &PathBuf) -> bool { ! ext::match_encoded(p.as_os_str().as_bytes()) }
 
-#[expect(clippy::ptr_arg, reason = "Needs to match filter() signature.")]
 /// # Find Default.
 ///
 /// This is a callback for `Dowser`. That library ensures the paths passed will
 /// be valid, canonical _files_; all we need to do is check the extensions.
 ///
-/// For this variation, we're looking for all the hard-coded "default" types.
-/// Refer to the main documentation or help screen for that list.
-fn find_default(p: &PathBuf) -> bool { ext::match_extension(p.as_os_str().as_bytes()) }
+/// For this variation, we're looking for all the hard-coded "default" types,
+/// plus or minus any user-specified `--ext`/`--no-ext` types. Refer to the
+/// main documentation or help screen for that list.
+fn find_default(p: &Path, types: &FileTypes) -> bool {
+	types.matches(p.as_os_str().as_bytes())
+}
@@ -0,0 +1,172 @@
+/*!
+# ChannelZ: Mirror
+
+This module handles `--out-dir`, mapping sources to their encoded copies
+under a separate output root with the same relative layout.
+*/
+
+use crate::ChannelZError;
+use std::{
+	collections::HashMap,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+
+
+#[derive(Debug, Clone, Default)]
+/// # Output Directory.
+///
+/// This holds the output root along with the (canonical) input roots the
+/// mirrored paths are relative to.
+pub(super) struct OutDir {
+	/// # Output Root.
+	dir: PathBuf,
+
+	/// # Input Roots.
+	///
+	/// These are kept sorted longest-first so nested roots win.
+	roots: Vec<PathBuf>,
+}
+
+impl OutDir {
+	/// # New.
+	///
+	/// The output root is made absolute (and canonical, if it already
+	/// exists) so it can be compared against crawled paths.
+	pub(super) fn new<P: AsRef<Path>>(dir: P, roots: &[PathBuf]) -> Option<Self> {
+		let dir = dir.as_ref();
+		let dir = std::fs::canonicalize(dir).or_else(|_| std::path::absolute(dir)).ok()?;
+
+		let mut roots = roots.to_vec();
+		roots.sort_unstable_by(|a, b| b.as_os_str().len().cmp(&a.as_os_str().len()).then_with(|| a.cmp(b)));
+		roots.dedup();
+
+		Some(Self { dir, roots })
+	}
+
+	/// # Output Root.
+	pub(super) fn dir(&self) -> &Path { &self.dir }
+
+	/// # Map Source.
+	///
+	/// Return the path `src` should be mirrored to, minus the encoding
+	/// extension, or `None` if it isn't under any of the input roots.
+	pub(super) fn map(&self, src: &Path) -> Option<PathBuf> {
+		for root in &self.roots {
+			// The source _is_ the root.
+			if src == root {
+				if let Some(name) = src.file_name() { return Some(self.dir.join(name)); }
+			}
+			// The source is under the root.
+			else if let Ok(rel) = src.strip_prefix(root) {
+				return Some(self.dir.join(rel));
+			}
+		}
+
+		None
+	}
+
+	/// # Check Sources.
+	///
+	/// Make sure each source maps to a path of its own under the output root.
+	///
+	/// ## Errors
+	///
+	/// Returns an error if a source is outside every input root — e.g. by way
+	/// of a symlink — or would overwrite another's mirror.
+	pub(super) fn check_sources(&self, srcs: &[PathBuf]) -> Result<(), ChannelZError> {
+		let mut seen = HashMap::with_capacity(srcs.len());
+		for src in srcs {
+			let dst = self.map(src)
+				.ok_or_else(|| ChannelZError::OutDirRoot(src.to_string_lossy().into_owned()))?;
+			if let Some(other) = seen.insert(dst, src) {
+				return Err(ChannelZError::OutDirCollision(format!(
+					"{} and {}",
+					other.display(),
+					src.display(),
+				)));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// # Unmap (Mirrored) Path.
+	///
+	/// Return the possible source(s) for a mirrored path (minus the encoding
+	/// extension).
+	pub(super) fn unmap(&self, dst: &Path) -> Vec<PathBuf> {
+		let Ok(rel) = dst.strip_prefix(&self.dir) else { return Vec::new(); };
+		self.roots.iter()
+			.filter_map(|root|
+				if root.is_dir() { Some(root.join(rel)) }
+				else if root.file_name().is_some_and(|n| rel.as_os_str() == n) {
+					Some(root.clone())
+				}
+				else { None }
+			)
+			.collect()
+	}
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_map() {
+		let out = OutDir::new(
+			"/tmp/channelz-mirror",
+			&[PathBuf::from("/srv/www"), PathBuf::from("/srv/www/assets/app.js")],
+		).expect("Unable to build OutDir.");
+
+		assert_eq!(
+			out.map(Path::new("/srv/www/css/site.css")).as_deref(),
+			Some(Path::new("/tmp/channelz-mirror/css/site.css")),
+		);
+		assert_eq!(
+			out.map(Path::new("/srv/www/assets/app.js")).as_deref(),
+			Some(Path::new("/tmp/channelz-mirror/app.js")),
+		);
+		assert!(out.map(Path::new("/elsewhere/site.css")).is_none());
+
+		// Unmapping only works for roots that exist on disk, but files can
+		// still be matched by name.
+		assert_eq!(
+			out.unmap(Path::new("/tmp/channelz-mirror/app.js")),
+			vec![PathBuf::from("/srv/www/assets/app.js")],
+		);
+		assert!(out.unmap(Path::new("/elsewhere/app.js")).is_empty());
+	}
+
+	#[test]
+	fn t_check_sources() {
+		let out = OutDir::new(
+			"/tmp/channelz-mirror",
+			&[PathBuf::from("/srv/a"), PathBuf::from("/srv/b")],
+		).expect("Unable to build OutDir.");
+
+		// Distinct relative paths are fine.
+		assert!(out.check_sources(&[
+			PathBuf::from("/srv/a/app.js"),
+			PathBuf::from("/srv/b/lib/app.js"),
+		]).is_ok());
+
+		// Shared ones aren't.
+		assert!(matches!(
+			out.check_sources(&[PathBuf::from("/srv/a/app.js"), PathBuf::from("/srv/b/app.js")]),
+			Err(ChannelZError::OutDirCollision(_)),
+		));
+
+		// Nor are sources outside the roots.
+		assert!(matches!(
+			out.check_sources(&[PathBuf::from("/srv/a/app.js"), PathBuf::from("/srv/c/app.js")]),
+			Err(ChannelZError::OutDirRoot(_)),
+		));
+	}
+}
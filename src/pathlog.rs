@@ -0,0 +1,168 @@
+/*!
+# ChannelZ: Path Log
+
+This module holds the generic path-and-kind log behind `Failures`, `Problems`,
+and `Plan`.
+*/
+
+use crate::print_msg;
+use fyi_msg::{
+	AnsiColor,
+	Msg,
+};
+use std::{
+	ops::Add,
+	path::PathBuf,
+};
+
+
+
+/// # Log Kind.
+///
+/// This is implemented by the enums noted against each path in a `PathLog`.
+pub(super) trait LogKind: Copy + Ord {
+	/// # One Entry Per Path?
+	///
+	/// If `true`, only the first (lowest) kind is kept for each path when
+	/// the log is finished; otherwise only exact duplicates are dropped.
+	const ONE_PER_PATH: bool = false;
+
+	/// # As Label.
+	fn as_str(self) -> &'static str;
+
+	/// # Color.
+	fn color(self) -> AnsiColor { AnsiColor::LightRed }
+
+	/// # Totals.
+	///
+	/// Return a closing summary for the (finished) log, if any.
+	fn totals(_log: &[(PathBuf, Self)]) -> Option<Msg> { None }
+}
+
+
+
+#[derive(Debug)]
+/// # Path Log.
+///
+/// This holds a list of paths along with what became — or would have become
+/// — of each.
+pub(super) struct PathLog<T: LogKind>(Vec<(PathBuf, T)>);
+
+impl<T: LogKind> Add for PathLog<T> {
+	type Output = Self;
+
+	#[inline]
+	fn add(mut self, mut other: Self) -> Self {
+		self.0.append(&mut other.0);
+		self
+	}
+}
+
+impl<T: LogKind> Default for PathLog<T> {
+	#[inline]
+	fn default() -> Self { Self::new() }
+}
+
+impl<T: LogKind> PathLog<T> {
+	/// # New.
+	pub(super) const fn new() -> Self { Self(Vec::new()) }
+
+	#[cfg(test)]
+	/// # As Slice.
+	pub(super) const fn as_slice(&self) -> &[(PathBuf, T)] { self.0.as_slice() }
+
+	/// # Is Empty?
+	pub(super) const fn is_empty(&self) -> bool { self.0.is_empty() }
+
+	/// # Length.
+	pub(super) const fn len(&self) -> usize { self.0.len() }
+
+	/// # Contains Kind?
+	pub(super) fn contains(&self, kind: T) -> bool {
+		self.0.iter().any(|(_, k)| *k == kind)
+	}
+
+	/// # Iterator.
+	pub(super) fn iter(&self) -> std::slice::Iter<'_, (PathBuf, T)> { self.0.iter() }
+
+	/// # Push.
+	pub(super) fn push(&mut self, path: PathBuf, kind: T) {
+		self.0.push((path, kind));
+	}
+
+	/// # Finish.
+	///
+	/// Sort the entries by path, dropping duplicates.
+	fn finish(&mut self) {
+		self.0.sort_unstable();
+		if T::ONE_PER_PATH { self.0.dedup_by(|a, b| a.0 == b.0); }
+		else { self.0.dedup(); }
+	}
+
+	/// # Summarize.
+	///
+	/// Print each entry to STDOUT — or STDERR if `stderr` — sorted by path,
+	/// followed by the totals, if any.
+	pub(super) fn summarize(&mut self, stderr: bool) {
+		self.finish();
+		for (path, kind) in &self.0 {
+			print_msg(
+				&Msg::new((kind.as_str(), kind.color()), path.to_string_lossy())
+					.with_newline(true),
+				stderr,
+			);
+		}
+
+		if let Some(msg) = T::totals(&self.0) { print_msg(&msg, stderr); }
+	}
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		Action,
+		FailKind,
+	};
+
+	#[test]
+	fn t_path_log() {
+		let mut a = PathLog::new();
+		assert!(a.is_empty(), "New logs should be empty.");
+		a.push(PathBuf::from("/b.gz"), FailKind::Write);
+		a.push(PathBuf::from("/a.br"), FailKind::Verify);
+
+		let mut b = PathLog::new();
+		b.push(PathBuf::from("/a.br"), FailKind::Remove);
+		b.push(PathBuf::from("/a.br"), FailKind::Verify);
+		b.push(PathBuf::from("/a"), FailKind::Read);
+
+		let mut c = a + b;
+		assert_eq!(c.len(), 5);
+		assert!(c.contains(FailKind::Read));
+		assert!(c.contains(FailKind::Write));
+		assert!(! c.contains(FailKind::Encode));
+
+		// Different kinds of trouble for the same path are all kept.
+		c.finish();
+		assert_eq!(
+			c.as_slice(),
+			[
+				(PathBuf::from("/a"), FailKind::Read),
+				(PathBuf::from("/a.br"), FailKind::Verify),
+				(PathBuf::from("/a.br"), FailKind::Remove),
+				(PathBuf::from("/b.gz"), FailKind::Write),
+			],
+			"Log wasn't combined/sorted/deduped correctly.",
+		);
+
+		// But plans only get one action per path.
+		let mut plan = PathLog::new();
+		plan.push(PathBuf::from("/a.br"), Action::Delete);
+		plan.push(PathBuf::from("/a.br"), Action::Replace);
+		plan.finish();
+		assert_eq!(plan.as_slice(), [(PathBuf::from("/a.br"), Action::Replace)]);
+	}
+}
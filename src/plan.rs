@@ -0,0 +1,95 @@
+/*!
+# ChannelZ: Plan
+
+This module holds the changes `--dry-run` would have made.
+*/
+
+use crate::{
+	LogKind,
+	PathLog,
+};
+use dactyl::NiceU64;
+use fyi_msg::{
+	AnsiColor,
+	Msg,
+};
+use std::path::{
+	Path,
+	PathBuf,
+};
+
+
+
+#[derive(Debug, Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+/// # Action.
+///
+/// The ways an encoded copy can be touched.
+pub(super) enum Action {
+	/// # Write (New).
+	Write,
+
+	/// # Replace (Existing).
+	Replace,
+
+	/// # Delete.
+	Delete,
+}
+
+impl LogKind for Action {
+	/// # One Entry Per Path.
+	///
+	/// A copy deleted by `--clean` and then rewritten is just replaced.
+	const ONE_PER_PATH: bool = true;
+
+	/// # As Label.
+	fn as_str(self) -> &'static str {
+		match self {
+			Self::Write =>   "   Write",
+			Self::Replace => " Replace",
+			Self::Delete =>  "  Delete",
+		}
+	}
+
+	/// # Color.
+	fn color(self) -> AnsiColor {
+		match self {
+			Self::Write => AnsiColor::LightGreen,
+			Self::Replace => AnsiColor::LightYellow,
+			Self::Delete => AnsiColor::LightRed,
+		}
+	}
+
+	/// # Totals.
+	fn totals(log: &[(PathBuf, Self)]) -> Option<Msg> {
+		let [written, replaced, deleted] = log.iter().fold([0_u64; 3], |mut acc, (_, action)| {
+			acc[*action as usize] += 1;
+			acc
+		});
+		Some(Msg::info(format!(
+			"Dry run: {} encoded {} would be written, {} replaced, and {} deleted.",
+			NiceU64::from(written),
+			if written == 1 { "copy" } else { "copies" },
+			NiceU64::from(replaced),
+			NiceU64::from(deleted),
+		)))
+	}
+}
+
+
+
+/// # Plan.
+///
+/// This holds the (encoded) paths that would have been written, replaced, or
+/// deleted, along with the action.
+pub(super) type Plan = PathLog<Action>;
+
+impl Plan {
+	/// # Push Write.
+	///
+	/// Same as `Plan::push`, but the action — `Write` or `Replace` — is
+	/// determined by whether or not the path already exists.
+	pub(super) fn push_write(&mut self, path: &Path) {
+		let action = if path.exists() { Action::Replace } else { Action::Write };
+		self.push(path.to_path_buf(), action);
+	}
+}
@@ -0,0 +1,208 @@
+/*!
+# ChannelZ: Report
+
+This module handles the (optional) machine-readable JSON run report.
+*/
+
+use crate::{
+	ChannelZError,
+	EncoderTotals,
+	Failures,
+	Flags,
+	ThreadTotals,
+};
+use std::{
+	fmt::Write,
+	io::Write as _,
+	ops::Add,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// # Report Destination.
+pub(super) enum ReportTo {
+	/// # STDOUT.
+	Stdout,
+
+	/// # File.
+	File(PathBuf),
+}
+
+impl From<String> for ReportTo {
+	fn from(src: String) -> Self {
+		if src == "-" { Self::Stdout }
+		else { Self::File(PathBuf::from(src)) }
+	}
+}
+
+
+
+#[derive(Debug, Default)]
+/// # Report.
+///
+/// This holds the per-file totals for the run.
+pub(super) struct Report(Vec<(PathBuf, EncoderTotals)>);
+
+impl Add for Report {
+	type Output = Self;
+
+	#[inline]
+	fn add(mut self, mut other: Self) -> Self {
+		self.0.append(&mut other.0);
+		self
+	}
+}
+
+impl Report {
+	/// # New.
+	pub(super) const fn new() -> Self { Self(Vec::new()) }
+
+	/// # Push.
+	pub(super) fn push(&mut self, path: &Path, len: EncoderTotals) {
+		self.0.push((path.to_path_buf(), len));
+	}
+
+	/// # Render.
+	///
+	/// Return the report as a JSON string, complete with the errors and
+	/// grand totals. If `dry_run`, the would-be changes are marked as planned.
+	pub(super) fn render(
+		&mut self,
+		totals: &ThreadTotals,
+		errs: &Failures,
+		kinds: Flags,
+		dry_run: bool,
+	) -> String {
+		self.0.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+		let mut out = String::from("{\"files\":[");
+		for (k, (path, len)) in self.0.iter().enumerate() {
+			if k != 0 { out.push(','); }
+			len.write_json(&path.to_string_lossy(), kinds, dry_run, &mut out);
+		}
+
+		out.push_str("],\"errors\":");
+		errs.write_json(&mut out);
+
+		out.push_str(",\"totals\":");
+		totals.write_json(self.0.len(), errs.len(), kinds, &mut out);
+		out.push_str("}\n");
+
+		out
+	}
+
+	/// # Save.
+	///
+	/// Write the report to STDOUT or a file.
+	///
+	/// ## Errors
+	///
+	/// Returns an error if the report cannot be written.
+	pub(super) fn save(
+		&mut self,
+		dst: &ReportTo,
+		totals: &ThreadTotals,
+		errs: &Failures,
+		kinds: Flags,
+		dry_run: bool,
+	) -> Result<(), ChannelZError> {
+		let out = self.render(totals, errs, kinds, dry_run);
+		match dst {
+			ReportTo::Stdout => {
+				let mut stdout = std::io::stdout().lock();
+				stdout.write_all(out.as_bytes())
+					.and_then(|()| stdout.flush())
+					.map_err(|_| ChannelZError::Report(String::from("-")))
+			},
+			ReportTo::File(path) => write_atomic::write_file(path, out.as_bytes())
+				.map_err(|_| ChannelZError::Report(path.to_string_lossy().into_owned())),
+		}
+	}
+}
+
+
+
+/// # JSON String.
+///
+/// Append `src` to `out` as a quoted, escaped JSON string.
+pub(super) fn json_str(src: &str, out: &mut String) {
+	out.push('"');
+	for c in src.chars() {
+		match c {
+			'"' => { out.push_str("\\\""); },
+			'\\' => { out.push_str("\\\\"); },
+			'\n' => { out.push_str("\\n"); },
+			'\r' => { out.push_str("\\r"); },
+			'\t' => { out.push_str("\\t"); },
+			c if c.is_control() => { let _res = write!(out, "\\u{:04x}", u32::from(c)); },
+			c => { out.push(c); },
+		}
+	}
+	out.push('"');
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::FailKind;
+	use std::num::NonZeroU64;
+
+	#[test]
+	fn t_json_str() {
+		let mut out = String::new();
+		json_str("/a \"b\"\\c\n\u{1}é", &mut out);
+		assert_eq!(out, "\"/a \\\"b\\\"\\\\c\\n\\u0001é\"");
+	}
+
+	#[test]
+	fn t_report() {
+		let kinds = Flags::Brotli | Flags::Gzip;
+
+		let mut a = EncoderTotals::new(NonZeroU64::new(100).unwrap());
+		a.set_br(NonZeroU64::new(80).unwrap());
+		a.set_removed(Flags::Gzip);
+
+		let b = EncoderTotals::up_to_date(NonZeroU64::new(50).unwrap());
+
+		let mut totals = ThreadTotals::new();
+		totals += a;
+		totals += b;
+
+		let mut errs = Failures::new();
+		errs.push(PathBuf::from("/c.css"), FailKind::Read);
+
+		let mut report = Report::new();
+		report.push(Path::new("/b.css"), b);
+		report = report + {
+			let mut r = Report::new();
+			r.push(Path::new("/a.css"), a);
+			r
+		};
+
+		assert_eq!(
+			report.render(&totals, &errs, kinds, false),
+			concat!(
+				"{\"files\":[",
+				"{\"path\":\"/a.css\",\"raw\":100,\"br\":80,\"br_trials\":0,\"gz\":null,",
+				"\"written\":[\"br\"],\"skipped\":[],\"removed\":[\"gz\"]},",
+				"{\"path\":\"/b.css\",\"raw\":50,\"br\":null,\"br_trials\":0,\"gz\":null,",
+				"\"written\":[],\"skipped\":[\"br\",\"gz\"],\"removed\":[]}",
+				"],\"errors\":[{\"path\":\"/c.css\",\"kind\":\"read\"}],",
+				"\"totals\":{\"files\":2,\"raw\":150,\"br\":130,\"br_trials\":0,\"gz\":150,",
+				"\"skipped\":1,\"errors\":1}}\n",
+			),
+		);
+		// Dry runs only plan things.
+		let out = report.render(&totals, &errs, kinds, true);
+		assert!(out.contains("\"planned\":[\"br\"],\"skipped\":[],\"planned_removal\":[\"gz\"]"));
+		assert!(! out.contains("\"written\""));
+		assert!(! out.contains("\"removed\""));
+	}
+}
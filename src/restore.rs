@@ -0,0 +1,176 @@
+/*!
+# ChannelZ: Restore
+
+This module handles `--decode`, which restores the originals from their
+encoded copies, e.g. when all that's left of a build are the precompressed
+artifacts.
+*/
+
+use crate::{
+	dec,
+	Dictionary,
+	enc::copy_metadata,
+	ext,
+	FailKind,
+	Failures,
+	Flags,
+};
+use std::{
+	num::NonZeroUsize,
+	os::unix::ffi::OsStrExt,
+	path::Path,
+};
+
+
+
+/// # Default Decode Limit.
+///
+/// Decoding gives up if the output would exceed this many bytes, so a
+/// decompression bomb can't fill up the disk (or memory).
+pub(super) const DECODE_LIMIT: NonZeroUsize = NonZeroUsize::new(1024 * 1024 * 1024).unwrap();
+
+
+
+#[derive(Debug)]
+/// # Restorer.
+///
+/// This decodes encoded copies back to their originals, reusing the same
+/// buffer from file to file.
+pub(super) struct Restorer<'a> {
+	/// # Shared Dictionary.
+	///
+	/// This is required for `.dcb` and `.dcz` copies.
+	dict: Option<&'a Dictionary>,
+
+	/// # Output Size Limit.
+	max: usize,
+
+	/// # Preserve Metadata?
+	preserve: bool,
+
+	/// # Decoding Buffer.
+	buf: Vec<u8>,
+}
+
+impl<'a> Restorer<'a> {
+	/// # New.
+	pub(super) const fn new(dict: Option<&'a Dictionary>, max: usize, preserve: bool) -> Self {
+		Self { dict, max, preserve, buf: Vec::new() }
+	}
+
+	/// # Restore.
+	///
+	/// Decode the encoded copy at `src` and (atomically) save the result
+	/// alongside it, minus the encoding extension, copying over its metadata
+	/// if preserving.
+	///
+	/// Returns `true` if the original was restored, or `false` if it already
+	/// exists or something went wrong, in which case the latter is noted in
+	/// `errs`.
+	pub(super) fn restore(&mut self, src: &Path, errs: &mut Failures) -> bool {
+		let Some(kind) = ext::encoded_kind(src.as_os_str().as_bytes()) else { return false; };
+		let dst = src.with_extension("");
+		if std::fs::symlink_metadata(&dst).is_ok() { return false; }
+
+		let Ok(raw) = std::fs::read(src) else {
+			errs.push(src.to_path_buf(), FailKind::Read);
+			return false;
+		};
+
+		if ! dec::decode(kind, self.dict, &raw, &mut self.buf, self.max) {
+			errs.push(src.to_path_buf(), FailKind::Decode);
+			return false;
+		}
+
+		if write_atomic::write_file(&dst, &self.buf).is_err() {
+			errs.push(dst, FailKind::Write);
+			return false;
+		}
+
+		if self.preserve && let Ok(meta) = std::fs::metadata(src) {
+			copy_metadata(&meta, &dst);
+		}
+
+		true
+	}
+}
+
+/// # Decodable?
+///
+/// Returns `true` if the encoded kind can be decoded with or without a
+/// dictionary, as the case may be.
+pub(super) const fn decodable(kind: Flags, dict: bool) -> bool {
+	dict || ! matches!(kind, Flags::Dcb | Flags::Dcz)
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_restore() {
+		const RAW: &[u8] = b"body { color: red; } body { color: red; } body { color: red; }";
+
+		let dir = std::env::temp_dir();
+		if ! dir.is_dir() { return; }
+		let src = dir.join("channelz-restore.css");
+		let src_br = dir.join("channelz-restore.css.br");
+		let src_gz = dir.join("channelz-restore.css.gz");
+		let _res = std::fs::remove_file(&src);
+
+		// Gzip.
+		let mut writer = libdeflater::Compressor::new(libdeflater::CompressionLvl::best());
+		let mut enc = vec![0_u8; writer.gzip_compress_bound(RAW.len())];
+		let len = writer.gzip_compress(RAW, &mut enc).expect("Gzip encoding failed.");
+		enc.truncate(len);
+		std::fs::write(&src_gz, &enc).expect("Unable to write file.");
+
+		// Brotli.
+		let mut enc = Vec::new();
+		brotli::enc::BrotliCompress(
+			&mut std::io::Cursor::new(RAW),
+			&mut enc,
+			&brotli::enc::backward_references::BrotliEncoderParams::default(),
+		).expect("Brotli encoding failed.");
+		std::fs::write(&src_br, &enc).expect("Unable to write file.");
+
+		let mut errs = Failures::new();
+
+		// Too big.
+		let mut restorer = Restorer::new(None, RAW.len() - 1, false);
+		assert!(! restorer.restore(&src_gz, &mut errs), "Decode limit was ignored.");
+		assert!(! restorer.restore(&src_br, &mut errs), "Decode limit was ignored.");
+		assert!(! src.exists(), "Nothing should have been written.");
+		assert_eq!(errs.len(), 2);
+		assert!(errs.contains(FailKind::Decode));
+
+		// Just right.
+		let mut errs = Failures::new();
+		let mut restorer = Restorer::new(None, RAW.len(), true);
+		assert!(restorer.restore(&src_br, &mut errs), "Brotli restoration failed.");
+		assert_eq!(std::fs::read(&src).ok().as_deref(), Some(RAW));
+
+		// It shouldn't overwrite what's there.
+		assert!(! restorer.restore(&src_gz, &mut errs), "The original was overwritten.");
+		let _res = std::fs::remove_file(&src);
+		assert!(restorer.restore(&src_gz, &mut errs), "Gzip restoration failed.");
+		assert_eq!(std::fs::read(&src).ok().as_deref(), Some(RAW));
+		assert!(errs.is_empty(), "There shouldn't be any errors.");
+
+		// Garbage.
+		std::fs::write(&src_br, b"Not brotli.").expect("Unable to write file.");
+		let _res = std::fs::remove_file(&src);
+		assert!(! restorer.restore(&src_br, &mut errs), "Garbage was decoded.");
+		assert!(errs.contains(FailKind::Decode));
+
+		// Dictionaries are required for some formats.
+		assert!(decodable(Flags::Gzip, false));
+		assert!(! decodable(Flags::Dcb, false));
+		assert!(decodable(Flags::Dcz, true));
+
+		let _res = std::fs::remove_file(src_br);
+		let _res = std::fs::remove_file(src_gz);
+	}
+}
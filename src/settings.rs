@@ -0,0 +1,554 @@
+/*!
+# ChannelZ: Settings
+*/
+
+use crate::{
+	config,
+	BR_QUALITY,
+	BR_WINDOW,
+	BrotliMode,
+	ChannelZError,
+	Delta,
+	Dictionary,
+	EncoderOptions,
+	Exclude,
+	FileTypes,
+	Flags,
+	GZ_LEVEL,
+	GzipEngine,
+	MinSavings,
+	OutDir,
+	Pattern,
+	ReportTo,
+	restore::DECODE_LIMIT,
+	train::{
+		DICT_SIZE,
+		DICT_SIZE_DEFAULT,
+	},
+};
+use dowser::Dowser;
+use std::{
+	num::{
+		NonZeroU64,
+		NonZeroUsize,
+	},
+	ops::RangeInclusive,
+	path::{
+		Path,
+		PathBuf,
+	},
+};
+
+
+
+#[expect(clippy::struct_excessive_bools, reason = "These are independent switches.")]
+/// # Settings.
+///
+/// This holds the runtime settings parsed from the CLI.
+pub(super) struct Settings {
+	/// # Formats and Such.
+	pub(super) kinds: Flags,
+
+	/// # Paths.
+	pub(super) paths: Dowser,
+
+	/// # Show Progress?
+	pub(super) progress: bool,
+
+	/// # File Types.
+	pub(super) types: FileTypes,
+
+	/// # Exclusions.
+	pub(super) exclude: Exclude,
+
+	/// # Encoder Options.
+	pub(super) opts: EncoderOptions,
+
+	/// # Check Only?
+	pub(super) check: bool,
+
+	/// # Prune Orphans First?
+	pub(super) prune: bool,
+
+	/// # Decode (With Limit)?
+	///
+	/// If set, the encoded copies are decoded back to their originals
+	/// instead, so long as they're no bigger than this.
+	pub(super) decode: Option<NonZeroUsize>,
+
+	/// # Fail on Per-File Errors?
+	pub(super) strict: bool,
+
+	/// # JSON Report Destination.
+	pub(super) report: Option<ReportTo>,
+
+	/// # Output Directory.
+	pub(super) out_dir: Option<OutDir>,
+
+	/// # Shared Dictionary.
+	pub(super) dict: Option<Dictionary>,
+
+	/// # Delta (Previous Release).
+	pub(super) delta: Option<Delta>,
+
+	/// # Stream to STDOUT?
+	///
+	/// If true, `kinds` holds the one format to encode STDIN with.
+	pub(super) stdout: bool,
+}
+
+impl Settings {
+	#[expect(clippy::too_many_lines, reason = "There are a lot of options.")]
+	/// # From CLI.
+	///
+	/// Parse the settings from the command line arguments.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if any of the arguments are invalid or no
+	/// encoders are enabled. Help and version requests are returned as
+	/// "errors" too.
+	pub(super) fn from_cli() -> Result<Self, ChannelZError> {
+		argyle::argue! {
+			Br            "--br",
+			BrMustBeatGz  "--br-must-beat-gz",
+			Check         "--check",
+			Clean         "--clean",
+			CleanOnly     "--clean-only",
+			Decode        "--decode",
+			DryRun        "--dry-run",
+			Exhaustive    "--exhaustive",
+			Force         "--force",
+			Gz            "--gz",
+			IgnoreFiles   "--ignore-files",
+			Incremental   "--incremental",
+			NoBr          "--no-br",
+			NoGz          "--no-gz",
+			NoPreserve    "--no-preserve",
+			NoZst         "--no-zst",
+			Progress "-p" "--progress",
+			Prune         "--prune",
+			Stdout        "--stdout",
+			Strict        "--strict",
+			Verify        "--verify",
+			Zst           "--zst",
+			Help     "-h" "--help",
+			Version  "-V" "--version",
+
+			@options
+			BrMode        "--br-mode",
+			BrQuality     "--br-quality",
+			BrWindow      "--br-window",
+			Config        "--config",
+			DecodeLimit   "--decode-limit",
+			DeltaFrom     "--delta-from",
+			DeltaMap      "--delta-map",
+			DeltaPattern  "--delta-pattern",
+			Dictionary    "--dictionary",
+			Exclude       "--exclude",
+			Ext           "--ext",
+			GzipEngine    "--gzip-engine",
+			GzLevel       "--gz-level",
+			List     "-l" "--list",
+			MinSavings    "--min-savings",
+			MinSize       "--min-size",
+			NoExt         "--no-ext",
+			OutDir        "--out-dir",
+			Preset        "--preset",
+			Report        "--report",
+			Unset         "--unset",
+			ZopfliIter    "--zopfli-iterations",
+
+			@catchall-paths Path,
+		}
+
+		// Find the config file (and preset), if any, bailing early for help
+		// and version requests in case it's broken.
+		let mut config = None;
+		let mut preset = None;
+		let mut unset = Vec::new();
+		for arg in Argument::args_os() {
+			match arg {
+				Argument::Config(s) => { config.replace(PathBuf::from(s)); },
+				Argument::Preset(s) => { preset.replace(s); },
+				Argument::Unset(s) => { unset.push(s); },
+				Argument::Help => return Err(ChannelZError::PrintHelp),
+				Argument::Version => return Err(ChannelZError::PrintVersion),
+				_ => {},
+			}
+		}
+
+		// Its settings go first so the CLI can override them.
+		let config_src = config.or_else(config::find);
+		let config = match config_src.as_deref() {
+			Some(src) => config::load(src, preset.as_deref(), &unset)?,
+			None => match preset {
+				Some(s) => return Err(ChannelZError::Preset(s)),
+				None => Vec::new(),
+			},
+		};
+		let args = ArgumentIter::new(config.into_iter())
+			.map(|arg| (true, arg))
+			.chain(Argument::args_os().map(|arg| (false, arg)));
+
+		// Parse CLI arguments.
+		let mut kinds = Flags::Brotli | Flags::Gzip;
+		let mut paths = Dowser::default();
+		let mut progress = false;
+		let mut stdout = false;
+		let mut picked = Flags::None;
+		let mut check = false;
+		let mut prune = false;
+		let mut decode = false;
+		let mut decode_limit = DECODE_LIMIT;
+		let mut strict = false;
+		let mut report = None;
+		let mut out_dir = None;
+		let mut dict = None;
+		let mut delta_from = None;
+		let mut delta_map = None;
+		let mut delta_pattern = None;
+		let mut roots = Vec::new();
+		let mut types = FileTypes::default();
+		let mut exclude = Exclude::default();
+		let mut opts = EncoderOptions::default();
+		let mut gzip_engine = None;
+		let mut zopfli_iter = GzipEngine::ZOPFLI_ITERATIONS;
+		for (from_config, arg) in args {
+			match arg {
+				Argument::Br => { picked.set(Flags::Brotli); },
+				Argument::BrMustBeatGz => { opts.br_must_beat_gz = true; },
+				Argument::Check => { check = true; },
+				Argument::Clean => { kinds.set(Flags::Clean); },
+				Argument::CleanOnly => { kinds.set(Flags::CleanOnly); },
+				Argument::Decode => { decode = true; },
+				Argument::DryRun => { opts.dry_run = true; },
+				Argument::Exhaustive => { opts.exhaustive = true; },
+				Argument::Force => { kinds.set(Flags::Force); },
+				Argument::Gz => { picked.set(Flags::Gzip); },
+				Argument::IgnoreFiles => { exclude.set_ignore_files(); },
+				Argument::Incremental => { opts.incremental = true; },
+				Argument::NoBr => { kinds.unset(Flags::Brotli); },
+				Argument::NoGz => { kinds.unset(Flags::Gzip); },
+				Argument::NoPreserve => { opts.preserve = false; },
+				Argument::NoZst => { kinds.unset(Flags::Zstd); },
+				Argument::Progress => { progress = true; },
+				Argument::Prune => { prune = true; },
+				Argument::Stdout => { stdout = true; },
+				Argument::Strict => { strict = true; },
+				Argument::Verify => { opts.verify = true; },
+				Argument::Zst => {
+					kinds.set(Flags::Zstd);
+					// Only the CLI gets to choose the streaming format.
+					if ! from_config { picked.set(Flags::Zstd); }
+				},
+
+				Argument::Help => return Err(ChannelZError::PrintHelp),
+				Argument::Version => return Err(ChannelZError::PrintVersion),
+
+				Argument::BrMode(s) => {
+					opts.br_mode = BrotliMode::from_name(&s).ok_or(ChannelZError::BrMode(s))?;
+				},
+				Argument::BrQuality(s) => {
+					opts.br_quality = parse_range(&s, BR_QUALITY).ok_or(ChannelZError::BrQuality)?;
+				},
+				Argument::BrWindow(s) => {
+					opts.br_window = parse_range(&s, BR_WINDOW).ok_or(ChannelZError::BrWindow)?;
+				},
+				Argument::Config(_) | Argument::Preset(_) | Argument::Unset(_) => {},
+				Argument::DecodeLimit(s) => {
+					decode_limit = parse_size(&s).ok_or(ChannelZError::DecodeLimit)?;
+				},
+				Argument::DeltaFrom(s) => { delta_from.replace(s); },
+				Argument::DeltaMap(s) => { delta_map.replace(s); },
+				Argument::DeltaPattern(s) => { delta_pattern.replace(s); },
+				Argument::Dictionary(s) => {
+					dict.replace(Dictionary::load(&s).ok_or(ChannelZError::Dictionary(s))?);
+				},
+				Argument::Exclude(s) => { exclude.push(&s)?; },
+				Argument::Ext(s) => { types.push_extra(&s)?; },
+				Argument::GzipEngine(s) => { gzip_engine.replace(s); },
+				Argument::GzLevel(s) => {
+					opts.gz_level = parse_range(&s, GZ_LEVEL).ok_or(ChannelZError::GzLevel)?;
+				},
+				Argument::MinSavings(s) => {
+					opts.min_savings = MinSavings::from_value(&s).ok_or(ChannelZError::MinSavings)?;
+				},
+				Argument::MinSize(s) => {
+					opts.min_size = s.trim().parse::<u64>().map_err(|_| ChannelZError::MinSize)?;
+				},
+				Argument::NoExt(s) => { types.push_skip(&s)?; },
+				Argument::OutDir(s) => { out_dir.replace(s); },
+				Argument::Report(s) => { report.replace(ReportTo::from(s)); },
+				Argument::ZopfliIter(s) => {
+					zopfli_iter = s.trim().parse::<NonZeroU64>()
+						.map_err(|_| ChannelZError::ZopfliIterations)?;
+				},
+
+				Argument::List(s) =>
+					if s == "-" { push_paths_from_stdin(&mut paths, &mut roots); }
+					else {
+						let raw = std::fs::read_to_string(s).map_err(|_| ChannelZError::ListFile)?;
+						for line in raw.lines() { push_path(&mut paths, &mut roots, line); }
+					},
+
+				// Assume paths.
+				Argument::Path(s) => { push_path(&mut paths, &mut roots, s); },
+
+				// Mistakes? Those from the config are unknown keys.
+				Argument::Other(s) => return Err(match config_src.as_deref() {
+					Some(src) if from_config => config::key_error(src, s.trim_start_matches('-')),
+					_ => ChannelZError::InvalidCli(s),
+				}),
+				Argument::OtherOs(s) => return Err(ChannelZError::InvalidCli(s.to_string_lossy().into_owned())),
+			}
+		}
+
+		// Streaming is its own thing.
+		if stdout {
+			if
+				! roots.is_empty() ||
+				! matches!(picked, Flags::Brotli | Flags::Gzip | Flags::Zstd)
+			{
+				return Err(ChannelZError::Stdout);
+			}
+			kinds = picked;
+		}
+		// Otherwise --br and --gz don't mean anything.
+		else if picked.contains_any(Flags::Brotli | Flags::Gzip).is_some() {
+			return Err(ChannelZError::NoStdout);
+		}
+
+		// Nothing?
+		kinds.contains_any(Flags::All).ok_or(ChannelZError::NoEncoders)?;
+
+		// Sort out the delta business.
+		let pattern = match delta_pattern {
+			Some(s) => Pattern::new(&s).ok_or(ChannelZError::DeltaPattern(s))?,
+			None => Pattern::new(Pattern::DEFAULT).ok_or(ChannelZError::DeltaPattern(String::new()))?,
+		};
+		let delta =
+			if delta_from.is_some() || delta_map.is_some() {
+				if dict.is_some() { return Err(ChannelZError::DeltaDictionary); }
+				let mut delta = Delta::default();
+				if let Some(s) = delta_map {
+					delta.load_map(&s).ok_or(ChannelZError::DeltaMap(s))?;
+				}
+				if let Some(s) = delta_from {
+					delta.load_previous(&s, &roots, pattern).ok_or(ChannelZError::DeltaFrom(s))?;
+				}
+				if let Some(dir) = delta.dir() { paths = paths.without_path(dir); }
+				Some(delta)
+			}
+			else { None };
+
+		// The dictionary-compressed formats piggyback on brotli and zstd.
+		if ! stdout && (dict.is_some() || delta.is_some()) {
+			if kinds.contains(Flags::Brotli) { kinds.set(Flags::Dcb); }
+			if kinds.contains(Flags::Zstd) { kinds.set(Flags::Dcz); }
+		}
+
+		// Compile the exclusions.
+		exclude.finish(&roots)?;
+
+		// Sort out the gzip engine.
+		if let Some(s) = gzip_engine {
+			opts.gzip = GzipEngine::from_name(&s, zopfli_iter)
+				.ok_or(ChannelZError::GzipEngine(s))?;
+		}
+
+		// Sort out the output directory, making sure it doesn't get crawled.
+		let out_dir = match out_dir {
+			Some(s) => {
+				let out = OutDir::new(&s, &roots).ok_or(ChannelZError::OutDir(s))?;
+				paths = paths.without_path(out.dir());
+				Some(out)
+			},
+			None => None,
+		};
+
+		// Decoding has no dry run.
+		if decode && opts.dry_run { return Err(ChannelZError::DecodeDryRun); }
+		let decode = decode.then_some(decode_limit);
+		Ok(Self {
+			kinds, paths, progress, types, exclude, opts, check, prune, decode, strict, report,
+			out_dir, dict, delta, stdout,
+		})
+	}
+}
+
+
+
+/// # Training Settings.
+///
+/// This holds the runtime settings for the `train-dict` subcommand.
+pub(super) struct TrainSettings {
+	/// # Paths.
+	pub(super) paths: Dowser,
+
+	/// # Force?
+	pub(super) force: bool,
+
+	/// # File Types.
+	pub(super) types: FileTypes,
+
+	/// # Output File.
+	pub(super) output: PathBuf,
+
+	/// # Dictionary Size.
+	pub(super) size: NonZeroUsize,
+}
+
+impl TrainSettings {
+	/// # From CLI.
+	///
+	/// Parse the settings from the command line arguments following
+	/// `train-dict`.
+	///
+	/// ## Errors
+	///
+	/// This will return an error if any of the arguments are invalid or the
+	/// output file is missing. Help requests are returned as "errors" too.
+	pub(super) fn from_cli() -> Result<Self, ChannelZError> {
+		argyle::argue! {
+			Force         "--force",
+			Help     "-h" "--help",
+
+			@options
+			Ext           "--ext",
+			List     "-l" "--list",
+			NoExt         "--no-ext",
+			Output   "-o" "--output",
+			Size          "--size",
+
+			@catchall-paths Path,
+		}
+
+		// Parse CLI arguments.
+		let mut paths = Dowser::default();
+		let mut force = false;
+		let mut types = FileTypes::default();
+		let mut output = None;
+		let mut size = DICT_SIZE_DEFAULT;
+		let mut roots = Vec::new();
+		for arg in ArgumentIter::new(std::env::args_os().skip(2)) {
+			match arg {
+				Argument::Force => { force = true; },
+				Argument::Help => return Err(ChannelZError::PrintHelp),
+
+				Argument::Ext(s) => { types.push_extra(&s)?; },
+				Argument::NoExt(s) => { types.push_skip(&s)?; },
+				Argument::Output(s) => { output.replace(PathBuf::from(s)); },
+				Argument::Size(s) => {
+					size = parse_size(&s)
+						.filter(|n| DICT_SIZE.contains(&n.get()))
+						.ok_or(ChannelZError::DictSize)?;
+				},
+
+				Argument::List(s) =>
+					if s == "-" { push_paths_from_stdin(&mut paths, &mut roots); }
+					else {
+						let raw = std::fs::read_to_string(s).map_err(|_| ChannelZError::ListFile)?;
+						for line in raw.lines() { push_path(&mut paths, &mut roots, line); }
+					},
+
+				// Assume paths.
+				Argument::Path(s) => { push_path(&mut paths, &mut roots, s); },
+
+				// Mistakes?
+				Argument::Other(s) =>   return Err(ChannelZError::InvalidCli(s)),
+				Argument::OtherOs(s) => return Err(ChannelZError::InvalidCli(s.to_string_lossy().into_owned())),
+			}
+		}
+
+		let output = output.ok_or(ChannelZError::NoDictOutput)?;
+		Ok(Self { paths, force, types, output, size })
+	}
+}
+
+
+/// # Parse Number (In Range).
+///
+/// Parse a number from a CLI value, returning `None` if it is invalid or
+/// out of range.
+fn parse_range(src: &str, range: RangeInclusive<u8>) -> Option<u8> {
+	src.trim().parse::<u8>().ok().filter(|n| range.contains(n))
+}
+
+/// # Parse Size.
+///
+/// Parse a byte size from a CLI value, with an optional (binary) "K", "M", or
+/// "G" suffix, returning `None` if it is invalid or zero.
+fn parse_size(src: &str) -> Option<NonZeroUsize> {
+	let src = src.trim();
+	let (num, unit) = match src.as_bytes().last()? {
+		b'k' | b'K' => (&src[..src.len() - 1], 1024),
+		b'm' | b'M' => (&src[..src.len() - 1], 1024 * 1024),
+		b'g' | b'G' => (&src[..src.len() - 1], 1024 * 1024 * 1024),
+		_ => (src, 1),
+	};
+	num.trim().parse::<usize>().ok()
+		.and_then(|n| n.checked_mul(unit))
+		.and_then(NonZeroUsize::new)
+}
+
+/// # Push Path.
+///
+/// Queue up a path for crawling, noting its canonical form as an input root.
+///
+/// Blank lines (from list files) are ignored.
+fn push_path<P: AsRef<Path>>(paths: &mut Dowser, roots: &mut Vec<PathBuf>, path: P) {
+	let path = path.as_ref();
+	if path.as_os_str().is_empty() { return; }
+	if let Ok(root) = std::fs::canonicalize(path) {
+		paths.push_path(&root);
+		roots.push(root);
+	}
+}
+
+/// # Push Paths From STDIN.
+///
+/// Same as `push_path`, but for each (trimmed) line read from STDIN.
+fn push_paths_from_stdin(paths: &mut Dowser, roots: &mut Vec<PathBuf>) {
+	use std::io::IsTerminal;
+
+	let stdin = std::io::stdin();
+	if ! stdin.is_terminal() {
+		for line in stdin.lines() {
+			let Ok(line) = line else { break; };
+			push_path(paths, roots, line.trim());
+		}
+	}
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_parse_range() {
+		assert_eq!(parse_range("11", BR_QUALITY), Some(11));
+		assert_eq!(parse_range(" 0 ", BR_QUALITY), Some(0));
+		assert_eq!(parse_range("12", BR_QUALITY), None);
+		assert_eq!(parse_range("9", BR_WINDOW), None);
+		assert_eq!(parse_range("24", BR_WINDOW), Some(24));
+		assert_eq!(parse_range("0", GZ_LEVEL), None);
+		assert_eq!(parse_range("-1", GZ_LEVEL), None);
+		assert_eq!(parse_range("best", GZ_LEVEL), None);
+	}
+
+	#[test]
+	fn t_parse_size() {
+		assert_eq!(parse_size("65536"), NonZeroUsize::new(65_536));
+		assert_eq!(parse_size(" 64K "), NonZeroUsize::new(65_536));
+		assert_eq!(parse_size("64k"), NonZeroUsize::new(65_536));
+		assert_eq!(parse_size("2M"), NonZeroUsize::new(2_097_152));
+		assert_eq!(parse_size("1g"), NonZeroUsize::new(1_073_741_824));
+		assert_eq!(parse_size("0"), None);
+		assert_eq!(parse_size("K"), None);
+		assert_eq!(parse_size("64KB"), None);
+		assert_eq!(parse_size("-1K"), None);
+	}
+}
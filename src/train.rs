@@ -0,0 +1,337 @@
+/*!
+# ChannelZ: Dictionary Training
+
+This module handles the `train-dict` subcommand, which builds a raw shared
+dictionary — suitable for both brotli and zstd — from a sample of the files
+ChannelZ would otherwise encode.
+
+The approach is a simplified take on zstd's COVER algorithm: the sample is
+split into epochs, and from each the segment whose (8-byte) substrings turn
+up in the most files is kept, the best of them last.
+*/
+
+use crate::{
+	ChannelZError,
+	Dictionary,
+	EncoderOptions,
+	Flags,
+	ThreadTotals,
+	enc::Encoder,
+	find_all,
+	find_default,
+	settings::TrainSettings,
+};
+use dactyl::NiceU64;
+use fyi_msg::{
+	fyi_ansi::dim,
+	Msg,
+};
+use std::{
+	io::Read,
+	num::NonZeroUsize,
+	ops::RangeInclusive,
+	path::PathBuf,
+	thread,
+};
+
+
+
+/// # Dictionary Size Range.
+///
+/// Brotli can't make use of anything bigger than its largest window.
+pub(super) const DICT_SIZE: RangeInclusive<usize> = 1024..=16 * 1024 * 1024;
+
+/// # Default Dictionary Size.
+pub(super) const DICT_SIZE_DEFAULT: NonZeroUsize = NonZeroUsize::new(64 * 1024).unwrap();
+
+/// # D-mer Length.
+///
+/// Candidate segments are scored by the substrings of this length they
+/// contain.
+const DMER: usize = 8;
+
+/// # Segment Length.
+const SEGMENT: usize = 512;
+
+/// # Frequency Table Size (Log2).
+const TABLE_BITS: u32 = 22;
+
+/// # Sample Ratio.
+///
+/// The corpus is sampled up to this multiple of the dictionary size, which
+/// is plenty to go on without taking forever.
+const SAMPLE_RATIO: usize = 100;
+
+/// # Minimum Per-File Sample.
+const SAMPLE_MIN: usize = 4096;
+
+/// # Hold-Out Ratio.
+///
+/// Every so-many sampled files are kept out of training so the savings can
+/// be estimated against files the dictionary hasn't seen.
+const HOLDOUT: usize = 5;
+
+
+
+/// # Train Dictionary.
+///
+/// Crawl the paths, sample the files, build the dictionary, save it, and
+/// print the estimated savings with and without it.
+///
+/// Every `HOLDOUT`th sample is held back for the estimate. If there aren't
+/// enough samples to spare, the estimate is made — and labeled — in-sample
+/// instead, which is bound to be optimistic.
+///
+/// ## Errors
+///
+/// Returns an error if no files are found or the dictionary can't be saved.
+pub(super) fn train(settings: TrainSettings) -> Result<(), ChannelZError> {
+	let TrainSettings { paths, force, types, output, size } = settings;
+
+	// Find the files.
+	let mut paths: Vec<PathBuf> =
+		if force { paths.filter(find_all).collect() }
+		else { paths.filter(|p| find_default(p, &types)).collect() };
+	paths.sort();
+
+	// Sample and train.
+	let (samples, held) = hold_out(sample(paths, size.get().saturating_mul(SAMPLE_RATIO)));
+	let raw = build(&samples, size.get());
+	let dict = Dictionary::from_raw(raw).ok_or(ChannelZError::NoFiles)?;
+
+	// Save it.
+	write_atomic::write_file(&output, dict.raw())
+		.map_err(|_| ChannelZError::DictOutput(output.to_string_lossy().into_owned()))?;
+
+	Msg::success(format!(
+		concat!("Saved a {}-byte dictionary to ", dim!("{}"), "."),
+		NiceU64::from(dict.raw().len() as u64),
+		output.display(),
+	)).print();
+
+	// Estimate the savings, preferably for the held-out files.
+	let in_sample = held.is_empty();
+	let samples = if in_sample { samples } else { held };
+	let kinds = Flags::Brotli | Flags::Zstd | Flags::Dcb | Flags::Dcz;
+	let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+	let len = thread::scope(|s| {
+		let dict = &dict;
+		samples.chunks(samples.len().div_ceil(threads))
+			.map(|chunk| s.spawn(move || {
+				let mut enc = Encoder::new(kinds, EncoderOptions::default())
+					.with_dictionary(Some(dict));
+				let mut len = ThreadTotals::new();
+				for (path, raw) in chunk {
+					if let Some(len2) = enc.estimate(path, raw) { len += len2; }
+				}
+				len
+			}))
+			.collect::<Vec<_>>()
+			.into_iter()
+			.try_fold(ThreadTotals::new(), |acc, worker| worker.join().map(|len2| acc + len2))
+			.map_err(|_| ChannelZError::Jobserver)
+	})?;
+
+	Msg::info(format!(
+		"Estimated savings for the {} {} {}{}:",
+		NiceU64::from(samples.len() as u64),
+		if in_sample { "training" } else { "held-out" },
+		if samples.len() == 1 { "file" } else { "files" },
+		if in_sample { " (in-sample, so likely optimistic)" } else { "" },
+	)).print();
+	len.summarize(kinds, false);
+
+	Ok(())
+}
+
+/// # Hold Out.
+///
+/// Split the samples into training and estimation sets, holding back every
+/// `HOLDOUT`th sample for the latter. If there are too few samples to spare
+/// any, the estimation set is empty.
+fn hold_out<T>(samples: Vec<T>) -> (Vec<T>, Vec<T>) {
+	if samples.len() < HOLDOUT { return (samples, Vec::new()); }
+	let mut k = 0;
+	samples.into_iter().partition(|_| {
+		k += 1;
+		k % HOLDOUT != 0
+	})
+}
+
+/// # Sample the Corpus.
+///
+/// Read (up to) `cap` bytes total from the files, spread evenly between
+/// them. If there are too many files for that, only every so-many of them
+/// are sampled.
+fn sample(paths: Vec<PathBuf>, cap: usize) -> Vec<(PathBuf, Vec<u8>)> {
+	if paths.is_empty() { return Vec::new(); }
+
+	let step = paths.len().saturating_mul(SAMPLE_MIN).div_ceil(cap).max(1);
+	let limit = (cap / paths.len().div_ceil(step)).max(SAMPLE_MIN) as u64;
+
+	let mut out = Vec::new();
+	for path in paths.into_iter().step_by(step) {
+		let mut raw = Vec::new();
+		if
+			std::fs::File::open(&path).and_then(|f| f.take(limit).read_to_end(&mut raw)).is_ok() &&
+			! raw.is_empty()
+		{
+			out.push((path, raw));
+		}
+	}
+
+	out
+}
+
+/// # Build Dictionary.
+///
+/// Build a raw dictionary of (up to) `size` bytes from the samples.
+fn build(samples: &[(PathBuf, Vec<u8>)], size: usize) -> Vec<u8> {
+	// Count the number of samples each d-mer appears in.
+	let mut freq = vec![0_u32; 1 << TABLE_BITS];
+	let mut seen = vec![0_u32; 1 << TABLE_BITS];
+	for ((_, raw), id) in samples.iter().zip(1_u32..) {
+		for w in raw.windows(DMER) {
+			let h = dmer(w);
+			if seen[h] != id {
+				seen[h] = id;
+				freq[h] += 1;
+			}
+		}
+	}
+	drop(seen);
+
+	// If everything fits, there's nothing to choose.
+	let corpus = samples.iter().flat_map(|(_, raw)| raw.iter().copied()).collect::<Vec<u8>>();
+	if corpus.len() <= size { return corpus; }
+
+	// Pick the best segment from each epoch, zeroing out its d-mers so they
+	// don't get picked again.
+	let epochs = (size / SEGMENT).min(corpus.len() / SEGMENT).max(1);
+	let mut picks = Vec::with_capacity(epochs + 1);
+	for epoch in corpus.chunks(corpus.len() / epochs) {
+		if let Some((score, start)) = best_segment(epoch, &freq) {
+			let seg = &epoch[start..start + SEGMENT];
+			for w in seg.windows(DMER) { freq[dmer(w)] = 0; }
+			picks.push((score, seg));
+		}
+	}
+
+	// The most valuable segments go last, where they're cheapest to
+	// reference. If there are too many, the least valuable get dropped.
+	picks.sort_by_key(|(score, _)| *score);
+	let mut out = picks.into_iter().flat_map(|(_, seg)| seg.iter().copied()).collect::<Vec<u8>>();
+	if size < out.len() { out.drain(..out.len() - size); }
+	out
+}
+
+/// # Best Segment.
+///
+/// Return the score and starting position of the `SEGMENT`-sized window of
+/// `src` with the highest total d-mer frequency, if any.
+fn best_segment(src: &[u8], freq: &[u32]) -> Option<(u64, usize)> {
+	if src.len() < SEGMENT { return None; }
+
+	let scores = src.windows(DMER).map(|w| u64::from(freq[dmer(w)])).collect::<Vec<u64>>();
+	let width = SEGMENT - DMER + 1;
+	let mut score: u64 = scores[..width].iter().sum();
+	let mut best = (score, 0);
+	for k in 1..=scores.len() - width {
+		score = score - scores[k - 1] + scores[k + width - 1];
+		if best.0 < score { best = (score, k); }
+	}
+
+	if best.0 == 0 { None }
+	else { Some(best) }
+}
+
+/// # D-mer Hash.
+///
+/// Hash the first `DMER` bytes of `src` into a frequency table index.
+fn dmer(src: &[u8]) -> usize {
+	let mut buf = [0_u8; DMER];
+	buf.copy_from_slice(&src[..DMER]);
+	(u64::from_le_bytes(buf).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (64 - TABLE_BITS)) as usize
+}
+
+
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn t_build() {
+		const BOILERPLATE: &[u8] = b"<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">";
+
+		// Small corpora are used as-is.
+		let samples = vec![
+			(PathBuf::from("a.css"), b"body { color: red; }".to_vec()),
+			(PathBuf::from("b.css"), b"html { color: blue; }".to_vec()),
+		];
+		assert_eq!(build(&samples, 1024), b"body { color: red; }html { color: blue; }");
+
+		// Otherwise the shared boilerplate should win out over the noise.
+		let mut state = 1_u64;
+		let samples = (0..32)
+			.map(|k| {
+				let mut raw = Vec::new();
+				for _ in 0..2048 {
+					state ^= state << 13;
+					state ^= state >> 7;
+					state ^= state << 17;
+					raw.push(b'a' + (state % 26) as u8);
+				}
+				raw.extend_from_slice(BOILERPLATE);
+				(PathBuf::from(format!("{k}.html")), raw)
+			})
+			.collect::<Vec<_>>();
+
+		let dict = build(&samples, 1024);
+		assert_eq!(dict.len(), 1024, "Wrong dictionary size.");
+		assert!(
+			dict.windows(BOILERPLATE.len()).any(|w| w == BOILERPLATE),
+			"The shared boilerplate is missing from the dictionary.",
+		);
+	}
+
+	#[test]
+	fn t_hold_out() {
+		// Too few to spare.
+		let (train, held) = hold_out(vec![1, 2, 3, 4]);
+		assert_eq!(train, [1, 2, 3, 4]);
+		assert!(held.is_empty());
+
+		// Every fifth.
+		let (train, held) = hold_out((1..=12).collect());
+		assert_eq!(train, [1, 2, 3, 4, 6, 7, 8, 9, 11, 12]);
+		assert_eq!(held, [5, 10]);
+	}
+
+	#[test]
+	fn t_sample() {
+		let dir = std::env::temp_dir();
+		if ! dir.is_dir() { return; }
+		let paths = (0..4)
+			.map(|k| {
+				let path = dir.join(format!("channelz-train-{k}.txt"));
+				std::fs::write(&path, vec![b'a' + k; 10_000]).expect("Unable to write file.");
+				path
+			})
+			.collect::<Vec<_>>();
+
+		// Everybody gets an equal (but at least minimum) share.
+		let out = sample(paths.clone(), 20_000);
+		assert_eq!(out.len(), 4);
+		assert!(out.iter().all(|(_, raw)| raw.len() == 5000), "Wrong sample size.");
+
+		// Too many files for the cap.
+		let out = sample(paths.clone(), SAMPLE_MIN * 2);
+		assert_eq!(out.len(), 2);
+		assert_eq!(out[0].0, paths[0]);
+		assert_eq!(out[1].0, paths[2]);
+		assert!(out.iter().all(|(_, raw)| raw.len() == SAMPLE_MIN), "Wrong sample size.");
+
+		for path in paths { let _res = std::fs::remove_file(path); }
+	}
+}